@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE allocation ADD COLUMN qty INTEGER;
@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE order_line ADD COLUMN order_id VARCHAR(50);
+
+UPDATE order_line SET order_id = id WHERE order_id IS NULL;
@@ -0,0 +1,11 @@
+-- Add migration script here
+CREATE TABLE reservation (
+    id VARCHAR(36) PRIMARY KEY
+    , order_id VARCHAR(50) NOT NULL
+    , sku VARCHAR(100) NOT NULL
+    , qty INTEGER NOT NULL
+    , status VARCHAR(20) NOT NULL
+    , expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+    , created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+    , updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE batch ADD COLUMN warehouse VARCHAR(50);
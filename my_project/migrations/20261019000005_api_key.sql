@@ -0,0 +1,10 @@
+-- Add migration script here
+CREATE TABLE api_key (
+    id VARCHAR(36) PRIMARY KEY
+    , name VARCHAR(100) NOT NULL
+    , key_hash VARCHAR(64) NOT NULL
+    , scopes VARCHAR(100) NOT NULL
+    , created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+    , last_used_at TIMESTAMP WITH TIME ZONE
+    , revoked_at TIMESTAMP WITH TIME ZONE
+);
@@ -0,0 +1,10 @@
+-- Add migration script here
+ALTER TABLE reservation ADD COLUMN allocation_id VARCHAR(36);
+
+-- 既有的保留指向訂單在該 sku 的分配
+UPDATE reservation SET allocation_id = (
+    SELECT a.id FROM allocation a
+    JOIN order_line o ON a.order_line_id = o.id
+    WHERE o.order_id = reservation.order_id AND o.sku = reservation.sku
+    LIMIT 1
+) WHERE status = 'held';
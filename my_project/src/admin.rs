@@ -0,0 +1,101 @@
+use axum::{
+    Json, Router, debug_handler,
+    extract::{Path, State},
+    http::StatusCode,
+    middleware::from_fn_with_state,
+    response::IntoResponse,
+    routing::{delete, get},
+};
+
+use crate::{
+    api_base::api_errors::ApiError, api_keys, auth, configures::Role, sitemaps::app_state::AppState,
+};
+
+/// 限 admin 角色
+pub fn admin_routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/admin/log-level",
+            get(log_level_handler).put(set_log_level_handler),
+        )
+        .route(
+            "/admin/api-keys",
+            get(api_keys_handler).post(issue_api_key_handler),
+        )
+        .route("/admin/api-keys/{id}", delete(revoke_api_key_handler))
+        .route_layer(from_fn_with_state(Role::Admin, auth::require_role))
+}
+
+#[debug_handler]
+pub async fn log_level_handler(State(app_state): State<AppState>) -> impl IntoResponse {
+    Json(serde_json::json!({
+        "directive": app_state.runtime.log_level().current(),
+    }))
+}
+
+#[derive(serde::Deserialize)]
+pub struct LogLevelReq {
+    /// 同 `logger.level` 的格式，例如 `info,sqlx=warn`
+    pub directive: String,
+}
+
+#[debug_handler]
+pub async fn set_log_level_handler(
+    State(app_state): State<AppState>,
+    Json(req): Json<LogLevelReq>,
+) -> Result<impl IntoResponse, ApiError> {
+    app_state
+        .runtime
+        .log_level()
+        .set(&req.directive)
+        .map_err(ApiError::BadRequest)?;
+
+    tracing::info!("Log level changed to {}", req.directive);
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({ "directive": req.directive })),
+    ))
+}
+
+#[debug_handler]
+pub async fn api_keys_handler(
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    Ok(Json(api_keys::list(&app_state.db).await?))
+}
+
+#[derive(serde::Deserialize)]
+pub struct ApiKeyReq {
+    pub name: String,
+    pub scopes: Vec<Role>,
+}
+
+/// 明文金鑰只在這個 response 出現一次
+#[debug_handler]
+pub async fn issue_api_key_handler(
+    State(app_state): State<AppState>,
+    Json(req): Json<ApiKeyReq>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (api_key, key) = api_keys::issue(&app_state.db, &req.name, &req.scopes).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "id": api_key.id,
+            "name": api_key.name,
+            "scopes": api_key.roles(),
+            "created_at": api_key.created_at,
+            "key": key,
+        })),
+    ))
+}
+
+#[debug_handler]
+pub async fn revoke_api_key_handler(
+    State(app_state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    api_keys::revoke(&app_state.db, &id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,15 +1,23 @@
 use axum::response::IntoResponse;
 
+use crate::services::ServiceError;
+
 #[derive(Debug, thiserror::Error)]
 pub enum ApiError {
     #[error("Unauthorized: {0}")]
     Unauthorized(String),
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
     #[error("Internal Server Error: {0}")]
     InternalServerError(String),
     #[error("Bad Request: {0}")]
     BadRequest(String),
+    #[error("Not Found: {0}")]
+    NotFound(String),
     #[error("Field Error: {0}")]
     FieldError(String),
+    #[error("Concurrency Conflict: {0}")]
+    ConcurrencyConflict(String),
     #[error("Database Error: {0}")]
     DatabaseError(#[from] sqlx::Error),
 }
@@ -18,11 +26,14 @@ impl IntoResponse for ApiError {
     fn into_response(self) -> axum::response::Response {
         let (status, message) = match self {
             ApiError::Unauthorized(msg) => (axum::http::StatusCode::UNAUTHORIZED, msg),
+            ApiError::Forbidden(msg) => (axum::http::StatusCode::FORBIDDEN, msg),
             ApiError::InternalServerError(msg) => {
                 (axum::http::StatusCode::INTERNAL_SERVER_ERROR, msg)
             }
             ApiError::BadRequest(msg) => (axum::http::StatusCode::BAD_REQUEST, msg),
+            ApiError::NotFound(msg) => (axum::http::StatusCode::NOT_FOUND, msg),
             ApiError::FieldError(msg) => (axum::http::StatusCode::BAD_REQUEST, msg),
+            ApiError::ConcurrencyConflict(msg) => (axum::http::StatusCode::CONFLICT, msg),
             ApiError::DatabaseError(err) => (
                 axum::http::StatusCode::INTERNAL_SERVER_ERROR,
                 err.to_string(),
@@ -41,3 +52,13 @@ impl IntoResponse for ApiError {
             .unwrap()
     }
 }
+
+impl From<ServiceError> for ApiError {
+    fn from(err: ServiceError) -> Self {
+        match err {
+            ServiceError::ConcurrencyConflict(msg) => ApiError::ConcurrencyConflict(msg),
+            ServiceError::OutOfStock(msg) | ServiceError::Invalid(msg) => ApiError::BadRequest(msg),
+            ServiceError::Database(msg) => ApiError::InternalServerError(msg),
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+
+use crate::api_base::api_errors::ApiError;
+use crate::configures::Role;
+use crate::entities::api_keys::ApiKey;
+use crate::repositories::{DbPool, create, quote, read, read_one, update};
+
+/// 金鑰格式為 `<id>.<secret>`，id 用來查詢，secret 只以 SHA-256 保存
+const SEPARATOR: char = '.';
+
+fn random(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// secret 是 32 bytes 的亂數，不需要 argon2 這類慢速雜湊，每個 request 驗證也不會拖慢
+fn digest(secret: &str) -> String {
+    format!("{:x}", Sha256::digest(secret.as_bytes()))
+}
+
+/// 簽發新的 API key，回傳資料列與只會出現這一次的明文金鑰
+pub async fn issue(db: &DbPool, name: &str, scopes: &[Role]) -> Result<(ApiKey, String), ApiError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(ApiError::BadRequest("API key name is required".to_string()));
+    }
+    if scopes.is_empty() {
+        return Err(ApiError::BadRequest(
+            "API key requires at least one scope".to_string(),
+        ));
+    }
+
+    let id = random(12);
+    let secret = random(32);
+    let api_key = ApiKey {
+        id: id.clone(),
+        name: name.to_string(),
+        key_hash: digest(&secret),
+        scopes: scopes
+            .iter()
+            .map(Role::to_string)
+            .collect::<Vec<_>>()
+            .join(","),
+        created_at: Utc::now(),
+        last_used_at: None,
+        revoked_at: None,
+    };
+
+    // 名稱由呼叫端提供，欄位逐一跳脫
+    create(
+        db,
+        &format!(
+            "INSERT INTO {} (id, name, key_hash, scopes, created_at) VALUES ({}, {}, {}, {}, {})",
+            ApiKey::table_name(),
+            quote(&api_key.id),
+            quote(&api_key.name),
+            quote(&api_key.key_hash),
+            quote(&api_key.scopes),
+            quote(&api_key.created_at.to_rfc3339())
+        ),
+    )
+    .await?;
+
+    tracing::info!("Issued API key {} ({})", api_key.id, api_key.name);
+    Ok((api_key, format!("{}{}{}", id, SEPARATOR, secret)))
+}
+
+/// 已撤銷的 key 也列出，方便稽核
+pub async fn list(db: &DbPool) -> Result<Vec<ApiKey>, ApiError> {
+    Ok(read::<&DbPool, ApiKey>(
+        db,
+        &format!("{} ORDER BY created_at", ApiKey::select_sql(None)),
+    )
+    .await?)
+}
+
+/// 撤銷後立即失效；不存在或已撤銷時回傳 NotFound
+pub async fn revoke(db: &DbPool, id: &str) -> Result<(), ApiError> {
+    let revoked = update(
+        db,
+        &format!(
+            "UPDATE {} SET revoked_at = {} WHERE id = {} AND revoked_at IS NULL",
+            ApiKey::table_name(),
+            quote(&Utc::now().to_rfc3339()),
+            quote(id)
+        ),
+    )
+    .await?;
+
+    if revoked == 0 {
+        return Err(ApiError::NotFound(format!("No active API key {}", id)));
+    }
+    tracing::info!("Revoked API key {}", id);
+    Ok(())
+}
+
+/// 比較完整長度，耗時不因第一個不同的位元組位置而改變
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// 驗證明文金鑰並更新最後使用時間；更新失敗（例如 SQLite 忙碌）只記錄 log，不影響驗證結果
+pub async fn verify(db: &DbPool, key: &str) -> Result<ApiKey, ApiError> {
+    let invalid = || ApiError::Unauthorized("Invalid API key".to_string());
+    let (id, secret) = key.split_once(SEPARATOR).ok_or_else(invalid)?;
+
+    let mut api_key = read_one::<&DbPool, ApiKey>(
+        db,
+        &ApiKey::select_sql(Some(&format!("id = {} AND revoked_at IS NULL", quote(id)))),
+    )
+    .await?
+    .filter(|api_key| constant_time_eq(api_key.key_hash.as_bytes(), digest(secret).as_bytes()))
+    .ok_or_else(invalid)?;
+
+    let now = Utc::now();
+    let touched = update(
+        db,
+        &format!(
+            "UPDATE {} SET last_used_at = {} WHERE id = {}",
+            ApiKey::table_name(),
+            quote(&now.to_rfc3339()),
+            quote(&api_key.id)
+        ),
+    )
+    .await;
+
+    match touched {
+        Ok(_) => api_key.last_used_at = Some(now),
+        Err(err) => tracing::warn!(
+            "Failed to update last_used_at of API key {}: {}",
+            api_key.id,
+            err
+        ),
+    }
+    Ok(api_key)
+}
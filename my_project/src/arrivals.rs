@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use crate::repositories::DbPool;
+use tokio::task::JoinHandle;
+
+use crate::shutdown::Shutdown;
+use crate::{configures::AppConfig, events, messagebus, services};
+
+/// 將 ETA 已到的批次標記為到貨，回傳需要發布的 BatchArrived 事件
+pub async fn check(db: &DbPool) -> Result<Vec<events::BatchArrived>, String> {
+    let mut tx = db.begin().await.map_err(|e| e.to_string())?;
+
+    match services::arrive_batches(chrono::Utc::now(), &mut tx).await {
+        Ok(arrived) => {
+            tx.commit().await.map_err(|e| e.to_string())?;
+            Ok(arrived)
+        }
+        Err(err) => {
+            tx.rollback().await.map_err(|e| e.to_string())?;
+            Err(err)
+        }
+    }
+}
+
+/// 背景到貨檢查：定期將 ETA 已到的批次轉為庫存，並將 BatchArrived 事件送進 message bus
+pub fn spawn_checker(db: DbPool, config: Arc<AppConfig>, shutdown: Shutdown) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(config.arrival.check_interval());
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = shutdown.wait() => break,
+            }
+
+            match check(&db).await {
+                Ok(arrived) => {
+                    for event in arrived {
+                        if let Err(err) =
+                            messagebus::headle(events::Event::BatchArrived(event), &db, &config)
+                                .await
+                        {
+                            tracing::warn!("Failed to publish BatchArrived: {}", err);
+                        }
+                    }
+                }
+                Err(err) => tracing::error!("Batch arrival check failed: {}", err),
+            }
+        }
+
+        tracing::info!("Batch arrival checker stopped");
+    })
+}
@@ -0,0 +1,262 @@
+use argon2::password_hash::SaltString;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use axum::{
+    Json, Router, debug_handler,
+    extract::{FromRequestParts, Request, State},
+    http::{header, request::Parts},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use chrono::Utc;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+
+use crate::api_base::api_errors::ApiError;
+use crate::api_keys;
+use crate::configures::{AppConfig, Role, UserConfig};
+use crate::sitemaps::app_state::AppState;
+
+/// access token 呼叫 API；refresh token 只能換發新的 token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenKind {
+    Access,
+    Refresh,
+}
+
+/// token 的內容，驗證通過後放在 request extension，handler 可直接以 `Claims` 取用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub roles: Vec<Role>,
+    pub kind: TokenKind,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+impl Claims {
+    pub fn has(&self, role: Role) -> bool {
+        self.roles.contains(&role)
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Claims {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Claims>()
+            .cloned()
+            .ok_or_else(|| ApiError::Unauthorized("Missing bearer token".to_string()))
+    }
+}
+
+/// access 以 `secret.jwt_secret`、refresh 以 `secret.refresh_secret` 簽章，兩者不能互換使用
+fn secret(config: &AppConfig, kind: TokenKind) -> Result<&[u8], ApiError> {
+    let secret = match kind {
+        TokenKind::Access => &config.secret.jwt_secret,
+        TokenKind::Refresh => &config.secret.refresh_secret,
+    };
+    secret
+        .as_deref()
+        .filter(|secret| !secret.is_empty())
+        .map(str::as_bytes)
+        .ok_or_else(|| {
+            ApiError::InternalServerError("Authentication is not configured".to_string())
+        })
+}
+
+/// 簽發 HS256 token，有效時間取自 `auth.access_ttl_seconds` / `auth.refresh_ttl_seconds`
+pub fn issue(
+    config: &AppConfig,
+    username: &str,
+    roles: &[Role],
+    kind: TokenKind,
+) -> Result<String, ApiError> {
+    let now = Utc::now();
+    let ttl = match kind {
+        TokenKind::Access => config.auth.access_ttl(),
+        TokenKind::Refresh => config.auth.refresh_ttl(),
+    };
+    // 設定可能繞過驗證（builder），期限超出範圍時回傳錯誤而不是 panic
+    let exp = ttl
+        .and_then(|ttl| now.checked_add_signed(ttl))
+        .ok_or_else(|| {
+            ApiError::InternalServerError(format!("{:?} token lifetime is out of range", kind))
+        })?;
+    let claims = Claims {
+        sub: username.to_string(),
+        roles: roles.to_vec(),
+        kind,
+        iat: now.timestamp(),
+        exp: exp.timestamp(),
+    };
+
+    jsonwebtoken::encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(secret(config, kind)?),
+    )
+    .map_err(|e| ApiError::InternalServerError(e.to_string()))
+}
+
+/// 檢查簽章、演算法（只接受 HS256）、期限與 token 種類
+pub fn verify(config: &AppConfig, token: &str, kind: TokenKind) -> Result<Claims, ApiError> {
+    let claims = jsonwebtoken::decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(secret(config, kind)?),
+        &Validation::new(Algorithm::HS256),
+    )
+    .map_err(|e| ApiError::Unauthorized(format!("Invalid token: {}", e)))?
+    .claims;
+
+    if claims.kind != kind {
+        return Err(ApiError::Unauthorized(
+            "Invalid token: wrong token type".to_string(),
+        ));
+    }
+    Ok(claims)
+}
+
+/// 驗證 `Authorization: Bearer <access token>` 或服務間呼叫的 `Authorization: ApiKey <key>`，
+/// claims 放進 request extension
+pub async fn authenticate(
+    State(app_state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    let claims = if let Some(token) = authorization.strip_prefix("Bearer ") {
+        verify(&app_state.config, token.trim(), TokenKind::Access)?
+    } else if let Some(key) = authorization.strip_prefix("ApiKey ") {
+        let api_key = api_keys::verify(&app_state.db, key.trim()).await?;
+        tracing::Span::current().record("api_key_id", api_key.id.as_str());
+        // API key 沒有期限，撤銷前一直有效；iat/exp 只記錄驗證的時間
+        let now = Utc::now().timestamp();
+        Claims {
+            sub: format!("api_key:{}", api_key.id),
+            roles: api_key.roles(),
+            kind: TokenKind::Access,
+            iat: now,
+            exp: now,
+        }
+    } else {
+        return Err(ApiError::Unauthorized("Missing bearer token".to_string()));
+    };
+    request.extensions_mut().insert(claims);
+    Ok(next.run(request).await)
+}
+
+/// 以 `from_fn_with_state(Role::Sales, require_role)` 掛在 route 上，必須位於 `authenticate` 內層
+pub async fn require_role(
+    State(role): State<Role>,
+    claims: Claims,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    if !claims.has(role) {
+        return Err(ApiError::Forbidden(format!("Requires role {}", role)));
+    }
+    Ok(next.run(request).await)
+}
+
+pub fn auth_routes() -> Router<AppState> {
+    Router::new()
+        .route("/auth/token", post(token_handler))
+        .route("/auth/refresh", post(refresh_handler))
+}
+
+#[derive(Deserialize)]
+pub struct TokenReq {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Deserialize)]
+pub struct RefreshReq {
+    pub refresh_token: String,
+}
+
+#[derive(Serialize)]
+pub struct TokenRes {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: &'static str,
+    /// access token 的有效秒數
+    pub expires_in: i64,
+}
+
+fn tokens(config: &AppConfig, user: &UserConfig) -> Result<Json<TokenRes>, ApiError> {
+    Ok(Json(TokenRes {
+        access_token: issue(config, &user.username, &user.roles, TokenKind::Access)?,
+        refresh_token: issue(config, &user.username, &user.roles, TokenKind::Refresh)?,
+        token_type: "Bearer",
+        expires_in: config.auth.access_ttl().map_or(0, |ttl| ttl.num_seconds()),
+    }))
+}
+
+#[debug_handler]
+pub async fn token_handler(
+    State(app_state): State<AppState>,
+    Json(req): Json<TokenReq>,
+) -> Result<impl IntoResponse, ApiError> {
+    let config = &app_state.config;
+    let user = config.auth.user(&req.username);
+    // 帳號不存在時仍驗證一次固定的雜湊，回應時間不會透露帳號是否存在
+    let hash = user.map_or(DUMMY_PASSWORD_HASH, |user| user.password_hash.as_str());
+    let matches = password_matches(&req.password, hash);
+    let user = user
+        .filter(|_| matches)
+        .ok_or_else(|| ApiError::Unauthorized("Invalid username or password".to_string()))?;
+
+    tracing::info!("Issued token for {}", user.username);
+    tokens(config, user)
+}
+
+#[debug_handler]
+pub async fn refresh_handler(
+    State(app_state): State<AppState>,
+    Json(req): Json<RefreshReq>,
+) -> Result<impl IntoResponse, ApiError> {
+    let config = &app_state.config;
+    let claims = verify(config, &req.refresh_token, TokenKind::Refresh)?;
+    // 角色以目前的設定為準，已移除的帳號不能再換發
+    let user = config
+        .auth
+        .user(&claims.sub)
+        .ok_or_else(|| ApiError::Unauthorized(format!("Unknown user {}", claims.sub)))?;
+
+    tokens(config, user)
+}
+
+/// 與 `hash_password` 相同參數產生的雜湊，對應的密碼不會被使用
+const DUMMY_PASSWORD_HASH: &str = "$argon2id$v=19$m=19456,t=2,p=1$KJIr9Vj7Ps0V9nYLehD3yA$Kq2cnI8W3eG4du7V20Mmywxi/xUaCEYjUzCRlXNqKqw";
+
+fn password_matches(password: &str, hash: &str) -> bool {
+    match PasswordHash::new(hash) {
+        Ok(hash) => Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok(),
+        Err(err) => {
+            tracing::warn!("Invalid password hash in auth.users: {}", err);
+            false
+        }
+    }
+}
+
+/// 以隨機 salt 產生 argon2id 的 PHC 字串，填入 `auth.users[].password_hash`
+pub fn hash_password(password: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("argon2 with default parameters accepts any password")
+        .to_string()
+}
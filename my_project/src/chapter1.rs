@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::collections::HashSet;
 
 use chrono::{DateTime, Utc};
@@ -14,6 +15,8 @@ pub struct Batch {
     pub reference: String,
     pub sku: String,
     pub eta: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub warehouse: Option<String>,
     _purchased_quantity: u32,
     _allocated_lines: HashSet<OrderLine>,
 }
@@ -23,12 +26,24 @@ impl Batch {
         Batch {
             reference: reference.to_string(),
             sku: sku.to_string(),
-            eta: eta,
+            eta,
+            created_at: None,
+            warehouse: None,
             _purchased_quantity: qty,
             _allocated_lines: HashSet::new(),
         }
     }
 
+    pub fn with_created_at(mut self, created_at: DateTime<Utc>) -> Self {
+        self.created_at = Some(created_at);
+        self
+    }
+
+    pub fn with_warehouse(mut self, warehouse: Option<String>) -> Self {
+        self.warehouse = warehouse;
+        self
+    }
+
     pub fn allocate(&mut self, line: &OrderLine) {
         if self.can_allocate(line) {
             self._allocated_lines.insert(line.clone());
@@ -44,6 +59,22 @@ impl Batch {
         }
     }
 
+    /// 解除某訂單在此批次的所有分配，回傳釋放的數量
+    pub fn deallocate_order(&mut self, order_id: &str) -> u32 {
+        let released = self.allocated_quantity_for(order_id);
+        self._allocated_lines
+            .retain(|line| line.order_id != order_id);
+        released
+    }
+
+    pub fn allocated_quantity_for(&self, order_id: &str) -> u32 {
+        self._allocated_lines
+            .iter()
+            .filter(|line| line.order_id == order_id)
+            .map(|line| line.qty)
+            .sum()
+    }
+
     pub fn allocated_quantity(&self) -> u32 {
         self._allocated_lines.iter().map(|line| line.qty).sum()
     }
@@ -65,14 +96,11 @@ impl PartialEq for Batch {
 
 impl PartialOrd for Batch {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        if self.eta.is_none() && other.eta.is_none() {
-            Some(std::cmp::Ordering::Equal)
-        } else if self.eta.is_none() {
-            Some(std::cmp::Ordering::Less)
-        } else if other.eta.is_none() {
-            Some(std::cmp::Ordering::Greater)
-        } else {
-            self.eta.unwrap().partial_cmp(&other.eta.unwrap())
+        match (self.eta, other.eta) {
+            (None, None) => Some(std::cmp::Ordering::Equal),
+            (None, Some(_)) => Some(std::cmp::Ordering::Less),
+            (Some(_), None) => Some(std::cmp::Ordering::Greater),
+            (Some(a), Some(b)) => a.partial_cmp(&b),
         }
     }
 }
@@ -83,17 +111,156 @@ impl std::hash::Hash for Batch {
     }
 }
 
-pub fn allocate(line: &OrderLine, batches: Vec<&mut Batch>) -> Result<Option<String>, String> {
+/// 分配策略：決定候選批次的優先順序，排在前面的先分配
+pub trait AllocationStrategy: Send + Sync {
+    fn compare(&self, a: &Batch, b: &Batch) -> Ordering;
+}
+
+/// 倉庫現貨優先，其次為最早到貨（ETA）的在途批次
+pub struct StockFirstEarliestEta;
+
+impl AllocationStrategy for StockFirstEarliestEta {
+    fn compare(&self, a: &Batch, b: &Batch) -> Ordering {
+        a.partial_cmp(b).unwrap_or(Ordering::Equal)
+    }
+}
+
+/// 可用量足夠的批次中，選擇可用量最小者，保留大批次給大訂單
+pub struct SmallestSufficientBatch;
+
+impl AllocationStrategy for SmallestSufficientBatch {
+    fn compare(&self, a: &Batch, b: &Batch) -> Ordering {
+        a.available_quantity().cmp(&b.available_quantity())
+    }
+}
+
+/// 先進先出：依批次建立時間，沒有建立時間的排在最後
+pub struct FifoByCreation;
+
+impl AllocationStrategy for FifoByCreation {
+    fn compare(&self, a: &Batch, b: &Batch) -> Ordering {
+        match (a.created_at, b.created_at) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+}
+
+/// 最近倉庫優先：依目的地區域的倉庫偏好順序排序，同一倉庫內再套用原本的策略；
+/// 不在偏好清單中的倉庫排在後面，沒有倉庫的批次排在最後
+pub struct NearestWarehouse {
+    preference: Vec<String>,
+    fallback: Box<dyn AllocationStrategy>,
+}
+
+impl NearestWarehouse {
+    pub fn new(preference: Vec<String>, fallback: Box<dyn AllocationStrategy>) -> Self {
+        NearestWarehouse {
+            preference,
+            fallback,
+        }
+    }
+
+    fn rank(&self, batch: &Batch) -> usize {
+        match &batch.warehouse {
+            Some(warehouse) => self
+                .preference
+                .iter()
+                .position(|w| w == warehouse)
+                .unwrap_or(self.preference.len()),
+            None => self.preference.len() + 1,
+        }
+    }
+}
+
+impl AllocationStrategy for NearestWarehouse {
+    fn compare(&self, a: &Batch, b: &Batch) -> Ordering {
+        self.rank(a)
+            .cmp(&self.rank(b))
+            .then_with(|| self.fallback.compare(a, b))
+    }
+}
+
+/// 內建策略，可於設定檔中依商品選擇
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AllocationStrategyKind {
+    #[default]
+    StockFirst,
+    SmallestSufficient,
+    Fifo,
+}
+
+impl AllocationStrategyKind {
+    pub fn strategy(&self) -> Box<dyn AllocationStrategy> {
+        match self {
+            AllocationStrategyKind::StockFirst => Box::new(StockFirstEarliestEta),
+            AllocationStrategyKind::SmallestSufficient => Box::new(SmallestSufficientBatch),
+            AllocationStrategyKind::Fifo => Box::new(FifoByCreation),
+        }
+    }
+}
+
+/// 分配失敗的原因，呼叫端依種類處理，不必比對錯誤訊息
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AllocationError {
+    #[error("Out of stock for sku {0}")]
+    OutOfStock(String),
+    /// 同一訂單以不同數量重試
+    #[error("Order {order_id} is already allocated {qty} of sku {sku}")]
+    AlreadyAllocated {
+        order_id: String,
+        sku: String,
+        qty: u32,
+    },
+}
+
+pub fn allocate(
+    line: &OrderLine,
+    batches: Vec<&mut Batch>,
+) -> Result<Option<String>, AllocationError> {
+    allocate_with(line, batches, &StockFirstEarliestEta)
+}
+
+pub fn allocate_with(
+    line: &OrderLine,
+    batches: Vec<&mut Batch>,
+    strategy: &dyn AllocationStrategy,
+) -> Result<Option<String>, AllocationError> {
     let mut batch_vec: Vec<&mut Batch> = batches
         .into_iter()
         .filter(|b| b.can_allocate(line))
         .collect();
 
+    batch_vec.sort_by(|a, b| strategy.compare(a, b));
+
     if !batch_vec.is_empty() {
         batch_vec[0].allocate(line);
-        return Ok(Some(batch_vec[0].reference.clone()));
+        Ok(Some(batch_vec[0].reference.clone()))
     } else {
-        return Err(format!("Out of stock for sku {}", line.sku));
+        Err(AllocationError::OutOfStock(line.sku.clone()))
+    }
+}
+
+/// 分批分配結果：(批次, 數量) 列表與商品版本號
+pub type SplitAllocation = (Vec<(String, u32)>, i32);
+
+/// 分配結果：`Existing` 表示同一訂單以相同數量重試，沿用既有的 (批次, 數量)，不需要再寫入
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Allocated<T> {
+    New(T),
+    Existing(Vec<(String, u32)>),
+}
+
+impl<T> Allocated<T> {
+    /// 本次新分配的結果，重試時為 None
+    pub fn into_new(self) -> Option<T> {
+        match self {
+            Allocated::New(value) => Some(value),
+            Allocated::Existing(_) => None,
+        }
     }
 }
 
@@ -101,6 +268,7 @@ pub struct Product {
     pub sku: String,
     pub batches: Vec<Batch>,
     pub version_number: i32,
+    pub strategy: Box<dyn AllocationStrategy>,
 }
 
 impl Product {
@@ -109,27 +277,135 @@ impl Product {
             sku: sku.to_string(),
             version_number: 1,
             batches,
+            strategy: AllocationStrategyKind::default().strategy(),
         }
     }
 
-    pub fn allocate(&mut self, line: &OrderLine) -> Result<Option<(String, i32)>, String> {
+    pub fn with_strategy(mut self, strategy: Box<dyn AllocationStrategy>) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn allocate(
+        &mut self,
+        line: &OrderLine,
+    ) -> Result<Allocated<(String, i32)>, AllocationError> {
+        // 重試時回傳既有的批次，不在其他批次重複分配
+        if let Some(existing) = self.existing_allocation(line)? {
+            return Ok(Allocated::Existing(existing));
+        }
+
+        let strategy = &self.strategy;
         let mut batch_refs: Vec<&mut Batch> = self
             .batches
             .iter_mut()
             .filter(|b| b.can_allocate(line))
             .collect();
 
-        batch_refs.sort_by(|a, b| {
-            a.available_quantity()
-                .partial_cmp(&b.available_quantity())
-                .unwrap()
-        });
+        batch_refs.sort_by(|a, b| strategy.compare(a, b));
 
         if !batch_refs.is_empty() {
             batch_refs[0].allocate(line);
-            return Ok(Some((batch_refs[0].reference.clone(), self.version_number)));
+            Ok(Allocated::New((
+                batch_refs[0].reference.clone(),
+                self.version_number,
+            )))
         } else {
-            return Err(format!("Out of stock for sku {}", line.sku));
+            Err(AllocationError::OutOfStock(line.sku.clone()))
+        }
+    }
+
+    /// 分批分配：沒有單一批次足夠時，依策略順序將訂單明細拆分到多個批次
+    pub fn allocate_parts(
+        &mut self,
+        line: &OrderLine,
+    ) -> Result<Allocated<SplitAllocation>, AllocationError> {
+        // 已分配過的訂單直接回傳既有結果
+        if let Some(existing) = self.existing_allocation(line)? {
+            return Ok(Allocated::Existing(existing));
+        }
+
+        if let Ok(Allocated::New((batch_ref, version_number))) = self.allocate(line) {
+            return Ok(Allocated::New((
+                vec![(batch_ref, line.qty)],
+                version_number,
+            )));
+        }
+
+        let available: u32 = self
+            .batches
+            .iter()
+            .filter(|b| b.sku == line.sku)
+            .map(|b| b.available_quantity())
+            .sum();
+        if available < line.qty {
+            return Err(AllocationError::OutOfStock(line.sku.clone()));
         }
+
+        let strategy = &self.strategy;
+        let mut batch_refs: Vec<&mut Batch> = self
+            .batches
+            .iter_mut()
+            .filter(|b| b.sku == line.sku && b.available_quantity() > 0)
+            .collect();
+
+        batch_refs.sort_by(|a, b| strategy.compare(a, b));
+
+        let mut parts = Vec::new();
+        let mut remaining = line.qty;
+        for batch in batch_refs {
+            if remaining == 0 {
+                break;
+            }
+
+            let qty = remaining.min(batch.available_quantity());
+            batch.allocate(&OrderLine {
+                qty,
+                ..line.clone()
+            });
+            parts.push((batch.reference.clone(), qty));
+            remaining -= qty;
+        }
+
+        Ok(Allocated::New((parts, self.version_number)))
+    }
+
+    /// 訂單已分配時回傳既有的 (批次, 數量)；數量與重試的明細不同則視為錯誤，不會重新分配
+    fn existing_allocation(
+        &self,
+        line: &OrderLine,
+    ) -> Result<Option<Vec<(String, u32)>>, AllocationError> {
+        let existing = self.allocations_for(&line.order_id);
+        if existing.is_empty() {
+            return Ok(None);
+        }
+
+        let allocated: u32 = existing.iter().map(|(_, qty)| qty).sum();
+        if allocated != line.qty {
+            return Err(AllocationError::AlreadyAllocated {
+                order_id: line.order_id.clone(),
+                sku: line.sku.clone(),
+                qty: allocated,
+            });
+        }
+        Ok(Some(existing))
+    }
+
+    /// 訂單目前分配到的 (批次, 數量)
+    pub fn allocations_for(&self, order_id: &str) -> Vec<(String, u32)> {
+        self.batches
+            .iter()
+            .map(|b| (b.reference.clone(), b.allocated_quantity_for(order_id)))
+            .filter(|(_, qty)| *qty > 0)
+            .collect()
+    }
+
+    /// 解除訂單在所有批次的分配，回傳釋放的 (批次, 數量)
+    pub fn deallocate(&mut self, order_id: &str) -> Vec<(String, u32)> {
+        self.batches
+            .iter_mut()
+            .map(|b| (b.reference.clone(), b.deallocate_order(order_id)))
+            .filter(|(_, qty)| *qty > 0)
+            .collect()
     }
 }
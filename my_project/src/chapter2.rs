@@ -64,12 +64,14 @@ fn calculate_hash(path: &Path) -> String {
         .collect()
 }
 
+type Action = (&'static str, (Option<PathBuf>, Option<PathBuf>));
+
 fn determine_actions(
     source_hashes: &Vec<(PathBuf, String)>,
     target_hashes: &Vec<(PathBuf, String)>,
     source: &str,
     target: &str,
-) -> Vec<(&'static str, (Option<PathBuf>, Option<PathBuf>))> {
+) -> Vec<Action> {
     let mut actions = Vec::new();
 
     for (path, hash) in source_hashes {
@@ -1,22 +1,47 @@
-use std::collections::HashMap;
-
 use crate::{
     api_base::api_errors::ApiError,
-    repositories::{read_one, update},
-    services,
-    sitemaps::app_state::AppState,
+    auth,
+    chapter1::Allocated,
+    configures::Role,
+    metrics, services,
+    sitemaps::{self, app_state::AppState},
+    views,
 };
 use axum::{
-    Json, Router, debug_handler, extract::State, http::StatusCode, response::IntoResponse,
-    routing::post,
+    Json, Router, debug_handler,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    middleware::from_fn_with_state,
+    response::IntoResponse,
+    routing::{get, post},
 };
-use chrono::NaiveDateTime;
-use sqlx::SqliteConnection;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 
+/// 需要登入：配貨與保留限 sales，進貨限 inventory
 pub fn logic_routes() -> Router<AppState> {
-    Router::new()
+    let sales = Router::new()
         .route("/allocate", post(allocate_handler))
+        .route("/deallocate", post(deallocate_handler))
+        .route("/orders/{order_id}/allocate", post(allocate_order_handler))
+        .route("/reserve", post(reserve_handler))
+        .route(
+            "/reservations/{order_id}/confirm",
+            post(confirm_reservation_handler),
+        )
+        .route_layer(from_fn_with_state(Role::Sales, auth::require_role));
+
+    let inventory = Router::new()
         .route("/add_batch", post(add_batch_handler))
+        .route_layer(from_fn_with_state(Role::Inventory, auth::require_role));
+
+    sales.merge(inventory)
+}
+
+pub fn view_routes() -> Router<AppState> {
+    Router::new()
+        .route("/products", get(list_products_handler))
+        .route("/products/{sku}", get(product_handler))
+        .route("/batches/{reference}", get(batch_handler))
 }
 
 #[derive(serde::Deserialize)]
@@ -24,6 +49,11 @@ pub struct AllocateReq {
     pub id: String,
     pub sku: String,
     pub qty: u32,
+    /// 允許將訂單明細拆分到多個批次
+    #[serde(default)]
+    pub partial: bool,
+    /// 目的地區域，用於選擇最近的倉庫
+    pub region: Option<String>,
 }
 
 #[debug_handler]
@@ -31,76 +61,287 @@ pub async fn allocate_handler(
     State(app_state): State<AppState>,
     Json(req): Json<AllocateReq>,
 ) -> Result<impl IntoResponse, ApiError> {
+    sitemaps::record_span(Some(&req.sku), Some(&req.id));
     let db = &app_state.db;
     let mut tx = db.begin().await.unwrap();
 
-    let allocate = services::allocate(&req.id, &req.sku, req.qty, &mut tx).await;
-    match allocate {
-        Ok(option) => {
-            let version_number: Option<(i32,)> = read_one::<&mut SqliteConnection, (i32,)>(
-                &mut *tx,
-                &format!(
-                    "SELECT MAX(version_number) FROM product WHERE sku = '{}'",
-                    req.sku
-                ),
-            )
-            .await
-            .unwrap();
-
-            if let Some(batch_ref) = option {
-                // 檢查版本號是否衝突
-                if let Some(version_number) = version_number {
-                    if version_number.0 == batch_ref.1 {
-                        tx.rollback().await.unwrap();
-                        return Err(ApiError::BadRequest(format!(
-                            "Version number conflict for sku {}",
-                            req.sku
-                        )));
-                    }
-                }
-
-                update::<&mut SqliteConnection>(
-                    &mut *tx,
-                    &format!(
-                        "UPDATE product SET version_number = {} WHERE sku = '{}'",
-                        batch_ref.1, req.sku
-                    ),
-                )
-                .await
-                .unwrap();
-
-                update::<&mut SqliteConnection>(
-                    &mut *tx,
-                    &format!(
-                        "UPDATE batch SET qty = qty - {} WHERE reference = '{}'",
-                        req.qty, batch_ref.0
-                    ),
-                )
-                .await
-                .unwrap();
-
-                tx.commit().await.unwrap();
-
-                return Ok((
-                    StatusCode::CREATED,
-                    Json({
-                        let mut res = HashMap::new();
-                        res.insert("batch_ref", batch_ref.0);
-                        res
-                    }),
-                ));
-            } else {
+    let allocate = if req.partial {
+        services::allocate_parts(
+            &req.id,
+            &req.sku,
+            req.qty,
+            req.region.as_deref(),
+            &app_state.config,
+            &mut tx,
+        )
+        .await
+    } else {
+        services::allocate(
+            &req.id,
+            &req.sku,
+            req.qty,
+            req.region.as_deref(),
+            &app_state.config,
+            &mut tx,
+        )
+        .await
+        .map(|allocated| match allocated {
+            Allocated::New((batch_ref, version)) => {
+                Allocated::New((vec![(batch_ref, req.qty)], version))
+            }
+            Allocated::Existing(parts) => Allocated::Existing(parts),
+        })
+    };
+
+    let parts = match allocate {
+        Ok(Allocated::New((parts, version))) => {
+            // 以版本號做樂觀鎖，版本已被其他交易更新時回應 409
+            if let Err(e) = services::save_version(&req.sku, version, &mut tx).await {
                 tx.rollback().await.unwrap();
+                metrics::metrics().allocation_error(&e);
+                return Err(e.into());
+            }
+
+            services::save_allocation(&req.id, &req.sku, req.qty, &parts, &mut tx).await?;
+            parts
+        }
+        // 重試：回傳既有的分配，不再寫入
+        Ok(Allocated::Existing(parts)) => parts,
+        Err(e) => {
+            tx.rollback().await.unwrap();
+            metrics::metrics().allocation_error(&e);
+            return Err(e.into());
+        }
+    };
+
+    let mut allocations = Vec::with_capacity(parts.len());
+    for (batch_ref, qty) in &parts {
+        let warehouse = services::warehouse_of(&mut tx, batch_ref).await?;
+        allocations.push(serde_json::json!({
+            "batch_ref": batch_ref,
+            "qty": qty,
+            "warehouse": warehouse,
+        }));
+    }
+
+    tx.commit().await.unwrap();
+    metrics::metrics().allocation("allocated");
+
+    Ok((
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "batch_ref": parts[0].0,
+            "warehouse": allocations[0]["warehouse"],
+            "allocations": allocations,
+        })),
+    ))
+}
+
+#[derive(serde::Deserialize)]
+pub struct AllocateOrderLineReq {
+    pub sku: String,
+    pub qty: u32,
+}
+
+#[derive(serde::Deserialize)]
+pub struct AllocateOrderReq {
+    pub lines: Vec<AllocateOrderLineReq>,
+    /// 目的地區域，用於選擇最近的倉庫
+    pub region: Option<String>,
+}
+
+#[debug_handler]
+pub async fn allocate_order_handler(
+    State(app_state): State<AppState>,
+    Path(order_id): Path<String>,
+    Json(req): Json<AllocateOrderReq>,
+) -> Result<impl IntoResponse, ApiError> {
+    sitemaps::record_span(None, Some(&order_id));
+    if req.lines.is_empty() {
+        return Err(ApiError::FieldError(format!(
+            "Order {} has no lines to allocate",
+            order_id
+        )));
+    }
+
+    let mut skus = std::collections::HashSet::new();
+    for line in &req.lines {
+        if !skus.insert(line.sku.as_str()) {
+            return Err(ApiError::FieldError(format!(
+                "Duplicate sku {} in order {}",
+                line.sku, order_id
+            )));
+        }
+    }
+
+    let lines = req
+        .lines
+        .iter()
+        .map(|line| (line.sku.clone(), line.qty))
+        .collect::<Vec<(String, u32)>>();
 
-                return Err(ApiError::BadRequest(format!(
-                    "Out of stock for sku {}",
-                    req.sku.clone()
-                )));
+    let db = &app_state.db;
+    let mut tx = db.begin().await.unwrap();
+
+    match services::allocate_order(
+        &order_id,
+        &lines,
+        req.region.as_deref(),
+        &app_state.config,
+        &mut tx,
+    )
+    .await
+    {
+        Ok(allocated) => {
+            let mut allocated_lines = Vec::with_capacity(allocated.len());
+            for ((sku, batch_ref), (_, qty)) in allocated.iter().zip(lines.iter()) {
+                let warehouse = services::warehouse_of(&mut tx, batch_ref).await?;
+                allocated_lines.push(serde_json::json!({
+                    "sku": sku,
+                    "qty": qty,
+                    "batch_ref": batch_ref,
+                    "warehouse": warehouse,
+                }));
             }
+
+            tx.commit().await.unwrap();
+            metrics::metrics().allocation("allocated");
+
+            Ok((
+                StatusCode::CREATED,
+                Json(serde_json::json!({
+                    "order_id": order_id,
+                    "lines": allocated_lines,
+                })),
+            ))
+        }
+        Err(e) => {
+            tx.rollback().await.unwrap();
+            metrics::metrics().allocation_error(&e);
+            Err(ApiError::from(e))
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct ReserveReq {
+    pub id: String,
+    pub sku: String,
+    pub qty: u32,
+    pub ttl_seconds: Option<u64>,
+}
+
+#[debug_handler]
+pub async fn reserve_handler(
+    State(app_state): State<AppState>,
+    Json(req): Json<ReserveReq>,
+) -> Result<impl IntoResponse, ApiError> {
+    sitemaps::record_span(Some(&req.sku), Some(&req.id));
+    let out_of_range = || ApiError::FieldError("ttl_seconds is out of range".to_string());
+    let ttl = match req.ttl_seconds {
+        // 過大的秒數無法換算成到期時間
+        Some(seconds) => i64::try_from(seconds)
+            .ok()
+            .and_then(chrono::Duration::try_seconds)
+            .ok_or_else(out_of_range)?,
+        None => app_state.config.reservation.ttl().ok_or_else(|| {
+            ApiError::InternalServerError("reservation.ttl_seconds is out of range".to_string())
+        })?,
+    };
+    let expires_at = Utc::now()
+        .checked_add_signed(ttl)
+        .ok_or_else(out_of_range)?;
+
+    let db = &app_state.db;
+    let mut tx = db.begin().await.unwrap();
+
+    match services::reserve(
+        &req.id,
+        &req.sku,
+        req.qty,
+        expires_at,
+        &app_state.config,
+        &mut tx,
+    )
+    .await
+    {
+        Ok(batch_ref) => {
+            tx.commit().await.unwrap();
+
+            Ok((
+                StatusCode::CREATED,
+                Json(serde_json::json!({
+                    "batch_ref": batch_ref,
+                    "expires_at": expires_at.to_rfc3339(),
+                })),
+            ))
+        }
+        Err(e) => {
+            tx.rollback().await.unwrap();
+            Err(ApiError::from(e))
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct ConfirmReservationReq {
+    pub sku: String,
+}
+
+#[debug_handler]
+pub async fn confirm_reservation_handler(
+    State(app_state): State<AppState>,
+    Path(order_id): Path<String>,
+    Json(req): Json<ConfirmReservationReq>,
+) -> Result<impl IntoResponse, ApiError> {
+    sitemaps::record_span(Some(&req.sku), Some(&order_id));
+    let db = &app_state.db;
+    let mut tx = db.begin().await.unwrap();
+
+    match services::confirm_reservation(&order_id, &req.sku, &mut tx).await {
+        Ok(_) => {
+            tx.commit().await.unwrap();
+            Ok((StatusCode::OK, "").into_response())
+        }
+        Err(e) => {
+            tx.rollback().await.unwrap();
+            Err(ApiError::BadRequest(e))
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct DeallocateReq {
+    pub id: String,
+    pub sku: String,
+}
+
+#[debug_handler]
+pub async fn deallocate_handler(
+    State(app_state): State<AppState>,
+    Json(req): Json<DeallocateReq>,
+) -> Result<impl IntoResponse, ApiError> {
+    sitemaps::record_span(Some(&req.sku), Some(&req.id));
+    let db = &app_state.db;
+    let mut tx = db.begin().await.unwrap();
+
+    match services::deallocate(&req.id, &req.sku, &mut tx).await {
+        Ok(released) => {
+            tx.commit().await.unwrap();
+
+            Ok(Json(serde_json::json!({
+                "released": released
+                    .iter()
+                    .map(|(batch_ref, qty)| serde_json::json!({
+                        "batch_ref": batch_ref,
+                        "qty": qty,
+                    }))
+                    .collect::<Vec<_>>(),
+            })))
         }
         Err(e) => {
             tx.rollback().await.unwrap();
-            return Err(ApiError::BadRequest(e));
+            Err(ApiError::from(e))
         }
     }
 }
@@ -111,6 +352,7 @@ pub struct AddBatchReq {
     pub sku: String,
     pub qty: u32,
     pub eta: Option<String>,
+    pub warehouse: Option<String>,
 }
 
 #[debug_handler]
@@ -118,6 +360,14 @@ pub async fn add_batch_handler(
     State(app_state): State<AppState>,
     Json(req): Json<AddBatchReq>,
 ) -> Result<impl IntoResponse, ApiError> {
+    sitemaps::record_span(Some(&req.sku), None);
+    let eta = match req.eta.as_deref() {
+        Some(s) => Some(parse_eta(s).ok_or_else(|| {
+            ApiError::FieldError(format!("Invalid eta {}, expected YYYY-MM-DD[ HH:MM:SS]", s))
+        })?),
+        None => None,
+    };
+
     let db = &app_state.db;
     let mut tx = db.begin().await.unwrap();
 
@@ -125,13 +375,8 @@ pub async fn add_batch_handler(
         &req.reference,
         &req.sku,
         req.qty,
-        req.eta.as_ref().and_then(|s| {
-            Some(
-                NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
-                    .unwrap()
-                    .and_utc(),
-            )
-        }),
+        eta,
+        req.warehouse.as_deref(),
         &mut tx,
     )
     .await
@@ -150,3 +395,59 @@ pub async fn add_batch_handler(
 
     Ok((StatusCode::CREATED, "").into_response())
 }
+
+#[debug_handler]
+pub async fn product_handler(
+    State(app_state): State<AppState>,
+    Path(sku): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    sitemaps::record_span(Some(&sku), None);
+    match views::product(&app_state.db, &sku).await? {
+        Some(product) => Ok(Json(product)),
+        None => Err(ApiError::NotFound(format!("Invalid sku {}", sku))),
+    }
+}
+
+#[debug_handler]
+pub async fn batch_handler(
+    State(app_state): State<AppState>,
+    Path(reference): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    match views::batch(&app_state.db, &reference).await? {
+        Some(batch) => Ok(Json(batch)),
+        None => Err(ApiError::NotFound(format!(
+            "Invalid batch reference {}",
+            reference
+        ))),
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct ListProductsReq {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+    pub sku_prefix: Option<String>,
+}
+
+#[debug_handler]
+pub async fn list_products_handler(
+    State(app_state): State<AppState>,
+    Query(req): Query<ListProductsReq>,
+) -> Result<impl IntoResponse, ApiError> {
+    let products = views::products(
+        &app_state.db,
+        req.page.unwrap_or(1),
+        req.per_page.unwrap_or(views::DEFAULT_PER_PAGE),
+        req.sku_prefix.as_deref(),
+    )
+    .await?;
+
+    Ok(Json(products))
+}
+
+fn parse_eta(s: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDate::parse_from_str(s, "%Y-%m-%d").map(|d| d.and_time(NaiveTime::MIN)))
+        .ok()
+        .map(|dt| dt.and_utc())
+}
@@ -0,0 +1,22 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::chapter1::AllocationStrategyKind;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AllocationConfig {
+    pub strategy: Option<AllocationStrategyKind>,
+    pub products: Option<HashMap<String, AllocationStrategyKind>>,
+}
+
+impl AllocationConfig {
+    /// 商品設定優先，其次為全域預設策略
+    pub fn strategy_for(&self, sku: &str) -> AllocationStrategyKind {
+        self.products
+            .as_ref()
+            .and_then(|products| products.get(sku).copied())
+            .or(self.strategy)
+            .unwrap_or_default()
+    }
+}
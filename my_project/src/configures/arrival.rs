@@ -0,0 +1,21 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ArrivalConfig {
+    pub check_interval_seconds: Option<u64>,
+    pub reallocate: Option<bool>,
+}
+
+impl ArrivalConfig {
+    /// 檢查 ETA 已到批次的間隔
+    pub fn check_interval(&self) -> Duration {
+        Duration::from_secs(self.check_interval_seconds.unwrap_or(60).max(1))
+    }
+
+    /// 批次到貨後是否將較晚到貨批次上的訂單明細改分配到此批次
+    pub fn reallocate(&self) -> bool {
+        self.reallocate.unwrap_or(false)
+    }
+}
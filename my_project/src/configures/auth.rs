@@ -0,0 +1,88 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// API 的角色，token 可帶多個
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// 進貨：`/add_batch`
+    Inventory,
+    /// 銷售：配貨、取消配貨與保留
+    Sales,
+    /// `/admin` 底下的維運操作
+    Admin,
+}
+
+impl Role {
+    pub const NAMES: &'static [&'static str] = &["inventory", "sales", "admin"];
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Role::Inventory => "inventory",
+            Role::Sales => "sales",
+            Role::Admin => "admin",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "inventory" => Ok(Role::Inventory),
+            "sales" => Ok(Role::Sales),
+            "admin" => Ok(Role::Admin),
+            _ => Err(format!(
+                "unknown role `{}`, expected one of {}",
+                name,
+                Role::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// 可以換取 token 的帳號，密碼以 argon2 的 PHC 字串保存（`architecture --hash-password` 產生）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UserConfig {
+    pub username: String,
+    pub password_hash: String,
+    #[serde(default)]
+    pub roles: Vec<Role>,
+}
+
+/// token 的有效時間與帳號；簽章用的金鑰在 `[secret]`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuthConfig {
+    pub access_ttl_seconds: Option<u64>,
+    pub refresh_ttl_seconds: Option<u64>,
+    #[serde(default)]
+    pub users: Vec<UserConfig>,
+}
+
+impl AuthConfig {
+    /// access token 預設 15 分鐘；秒數超出可表示的範圍時為 None
+    pub fn access_ttl(&self) -> Option<chrono::Duration> {
+        seconds(self.access_ttl_seconds.unwrap_or(900))
+    }
+
+    /// refresh token 預設 7 天
+    pub fn refresh_ttl(&self) -> Option<chrono::Duration> {
+        seconds(self.refresh_ttl_seconds.unwrap_or(7 * 24 * 3600))
+    }
+
+    pub fn user(&self, username: &str) -> Option<&UserConfig> {
+        self.users.iter().find(|user| user.username == username)
+    }
+}
+
+fn seconds(seconds: u64) -> Option<chrono::Duration> {
+    i64::try_from(seconds)
+        .ok()
+        .and_then(chrono::Duration::try_seconds)
+}
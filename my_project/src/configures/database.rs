@@ -1,38 +1,252 @@
+use std::collections::BTreeMap;
 use std::str::FromStr;
 use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
-use sqlx::SqlitePool;
-use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::pool::PoolOptions;
+use sqlx::postgres::PgConnectOptions;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqliteSynchronous};
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::configures::Profile;
+use crate::repositories::DbPool;
+
+/// 資料庫種類：SQLite 用於開發與測試，Postgres 用於正式環境
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DatabaseDriver {
+    #[default]
+    Sqlite,
+    Postgres,
+}
+
+impl DatabaseDriver {
+    pub const NAMES: &'static [&'static str] = &["sqlite", "postgres"];
+}
+
+/// SQLite 的 journal_mode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalMode {
+    Delete,
+    Truncate,
+    Persist,
+    Memory,
+    #[default]
+    Wal,
+    Off,
+}
+
+impl JournalMode {
+    pub const NAMES: &'static [&'static str] =
+        &["delete", "truncate", "persist", "memory", "wal", "off"];
+}
+
+impl From<JournalMode> for SqliteJournalMode {
+    fn from(mode: JournalMode) -> Self {
+        match mode {
+            JournalMode::Delete => SqliteJournalMode::Delete,
+            JournalMode::Truncate => SqliteJournalMode::Truncate,
+            JournalMode::Persist => SqliteJournalMode::Persist,
+            JournalMode::Memory => SqliteJournalMode::Memory,
+            JournalMode::Wal => SqliteJournalMode::Wal,
+            JournalMode::Off => SqliteJournalMode::Off,
+        }
+    }
+}
+
+/// SQLite 的 synchronous 層級
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Synchronous {
+    Off,
+    #[default]
+    Normal,
+    Full,
+    Extra,
+}
+
+impl Synchronous {
+    pub const NAMES: &'static [&'static str] = &["off", "normal", "full", "extra"];
+}
+
+impl From<Synchronous> for SqliteSynchronous {
+    fn from(level: Synchronous) -> Self {
+        match level {
+            Synchronous::Off => SqliteSynchronous::Off,
+            Synchronous::Normal => SqliteSynchronous::Normal,
+            Synchronous::Full => SqliteSynchronous::Full,
+            Synchronous::Extra => SqliteSynchronous::Extra,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct DatabaseConfig {
+    pub driver: Option<DatabaseDriver>,
     pub host: Option<String>,
     pub port: Option<u16>,
     pub user: Option<String>,
     pub password: Option<String>,
     pub database: Option<String>,
     pub schema: Option<String>,
+    pub auto_migrate: Option<bool>,
+    // 連線池，兩種 driver 共用
+    pub max_connections: Option<u32>,
+    pub min_connections: Option<u32>,
+    pub acquire_timeout_seconds: Option<u64>,
+    /// 0 表示閒置連線不回收
+    pub idle_timeout_seconds: Option<u64>,
+    // 以下只對 SQLite 生效
+    pub busy_timeout_ms: Option<u64>,
+    pub journal_mode: Option<JournalMode>,
+    pub synchronous: Option<Synchronous>,
+    pub foreign_keys: Option<bool>,
+    /// 每條連線建立時額外執行的 PRAGMA，例如 `cache_size = -20000`
+    pub pragmas: Option<BTreeMap<String, String>>,
 }
 
 impl DatabaseConfig {
-    pub async fn get_connection(&self) -> SqlitePool {
+    pub fn driver(&self) -> DatabaseDriver {
+        self.driver.unwrap_or_default()
+    }
+
+    /// 啟動時是否自動執行 migration，正式環境預設關閉
+    pub fn auto_migrate_for(&self, profile: Profile) -> bool {
+        self.auto_migrate.unwrap_or(!profile.is_production())
+    }
+
+    pub fn max_connections(&self) -> u32 {
+        self.max_connections.unwrap_or(10)
+    }
+
+    pub fn min_connections(&self) -> u32 {
+        self.min_connections.unwrap_or(0)
+    }
+
+    pub fn acquire_timeout(&self) -> Duration {
+        Duration::from_secs(self.acquire_timeout_seconds.unwrap_or(30))
+    }
+
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        match self.idle_timeout_seconds.unwrap_or(600) {
+            0 => None,
+            seconds => Some(Duration::from_secs(seconds)),
+        }
+    }
+
+    pub fn busy_timeout(&self) -> Duration {
+        Duration::from_millis(self.busy_timeout_ms.unwrap_or(5000))
+    }
+
+    pub fn journal_mode(&self) -> JournalMode {
+        self.journal_mode.unwrap_or_default()
+    }
+
+    pub fn synchronous(&self) -> Synchronous {
+        self.synchronous.unwrap_or_default()
+    }
+
+    pub fn foreign_keys(&self) -> bool {
+        self.foreign_keys.unwrap_or(true)
+    }
+
+    /// 實際生效的連線設定，啟動時寫進 log
+    pub fn summary(&self) -> String {
+        let idle = match self.idle_timeout() {
+            Some(timeout) => format!("{}s", timeout.as_secs()),
+            None => "never".to_string(),
+        };
+        let mut summary = format!(
+            "driver={} max_connections={} min_connections={} acquire_timeout={}s idle_timeout={}",
+            format!("{:?}", self.driver()).to_lowercase(),
+            self.max_connections(),
+            self.min_connections(),
+            self.acquire_timeout().as_secs(),
+            idle
+        );
+        if self.driver() == DatabaseDriver::Sqlite {
+            summary.push_str(&format!(
+                " busy_timeout={}ms journal_mode={} synchronous={} foreign_keys={}",
+                self.busy_timeout().as_millis(),
+                format!("{:?}", self.journal_mode()).to_lowercase(),
+                format!("{:?}", self.synchronous()).to_lowercase(),
+                self.foreign_keys()
+            ));
+            for (name, value) in self.pragmas.iter().flatten() {
+                summary.push_str(&format!(" {}={}", name, value));
+            }
+        }
+        summary
+    }
+
+    fn pool_options<DB: sqlx::Database>(&self) -> PoolOptions<DB> {
+        PoolOptions::new()
+            .max_connections(self.max_connections())
+            .min_connections(self.min_connections())
+            .acquire_timeout(self.acquire_timeout())
+            .idle_timeout(self.idle_timeout())
+    }
+
+    pub async fn get_connection(&self) -> Result<DbPool, sqlx::Error> {
+        tracing::info!("Database settings: {}", self.summary());
+
+        match self.driver() {
+            DatabaseDriver::Sqlite => self.sqlite().await,
+            DatabaseDriver::Postgres => self.postgres().await,
+        }
+    }
+
+    async fn sqlite(&self) -> Result<DbPool, sqlx::Error> {
         let conn_str = format!("sqlite://{}.db", self.database.as_deref().unwrap_or("mydb"),);
 
         tracing::info!("Connected to database at {}", conn_str);
 
-        let options = SqliteConnectOptions::from_str(&conn_str)
-            .unwrap()
-            .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
-            .synchronous(sqlx::sqlite::SqliteSynchronous::Normal)
-            .busy_timeout(Duration::from_secs(5))
+        let options = SqliteConnectOptions::from_str(&conn_str)?
+            .journal_mode(self.journal_mode().into())
+            .synchronous(self.synchronous().into())
+            .busy_timeout(self.busy_timeout())
+            .foreign_keys(self.foreign_keys())
             .create_if_missing(true);
+        let options = self
+            .pragmas
+            .iter()
+            .flatten()
+            .fold(options, |options, (name, value)| {
+                options.pragma(name.clone(), value.clone())
+            });
 
-        let pool = SqlitePoolOptions::new()
+        self.pool_options::<sqlx::Sqlite>()
             .connect_with(options)
             .await
-            .unwrap();
+            .map(DbPool::from)
+    }
+
+    /// `schema` 以 search_path 指定，migration 與查詢都在該 schema 下執行
+    async fn postgres(&self) -> Result<DbPool, sqlx::Error> {
+        let mut options = PgConnectOptions::new()
+            .host(self.host.as_deref().unwrap_or("localhost"))
+            .port(self.port.unwrap_or(5432))
+            .database(self.database.as_deref().unwrap_or("mydb"));
+        if let Some(user) = &self.user {
+            options = options.username(user);
+        }
+        if let Some(password) = &self.password {
+            options = options.password(password);
+        }
+        if let Some(schema) = &self.schema {
+            options = options.options([("search_path", schema.as_str())]);
+        }
 
-        pool
+        tracing::info!(
+            "Connected to database at postgres://{}:{}/{}",
+            options.get_host(),
+            options.get_port(),
+            options.get_database().unwrap_or_default()
+        );
+
+        self.pool_options::<sqlx::Postgres>()
+            .connect_with(options)
+            .await
+            .map(DbPool::from)
     }
 }
@@ -1,13 +1,23 @@
+use std::sync::{Arc, RwLock};
+
 use chrono::Local;
+use opentelemetry_sdk::trace::SdkTracerProvider;
 use serde::{Deserialize, Serialize};
 use tracing::Subscriber;
 use tracing::level_filters::LevelFilter;
 use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
 use tracing_subscriber::Layer;
 use tracing_subscriber::filter::EnvFilter;
+use tracing_subscriber::fmt::MakeWriter;
 use tracing_subscriber::fmt::format::Writer;
 use tracing_subscriber::fmt::time::FormatTime;
 use tracing_subscriber::prelude::*;
+use tracing_subscriber::{Registry, reload};
+
+use crate::configures::Profile;
+use crate::configures::rolling::{LogRotation, RollPolicy, RollingFile};
+use crate::configures::telemetry::{self, OtlpConfig};
+use crate::configures::validation;
 
 struct LocalTimer;
 
@@ -17,119 +27,277 @@ impl FormatTime for LocalTimer {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// console 的輸出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// 彩色、含檔名行號，方便開發時閱讀
+    Pretty,
+    /// 單行、無色碼，方便收集器處理
+    Compact,
+    /// 每行一個 JSON 物件，含 span 欄位，供 log pipeline 解析
+    Json,
+}
+
+impl LogFormat {
+    pub const NAMES: &'static [&'static str] = &["pretty", "compact", "json"];
+}
+
+/// 執行期間可更換的 log filter；未安裝全域 subscriber 時（例如測試）只記錄目前的設定
+#[derive(Clone)]
+pub struct LogLevel {
+    directive: Arc<RwLock<String>>,
+    handle: Option<reload::Handle<EnvFilter, Registry>>,
+}
+
+impl LogLevel {
+    pub fn detached(directive: &str) -> Self {
+        LogLevel {
+            directive: Arc::new(RwLock::new(directive.to_string())),
+            handle: None,
+        }
+    }
+
+    pub fn current(&self) -> String {
+        self.directive.read().unwrap().clone()
+    }
+
+    /// 更換 filter，格式同 `logger.level`，例如 `info,sqlx=warn`
+    pub fn set(&self, directive: &str) -> Result<(), String> {
+        if let Some(invalid) = validation::invalid_directive(directive) {
+            return Err(format!("Invalid log level {}", invalid));
+        }
+        let filter = EnvFilter::try_new(directive).map_err(|e| e.to_string())?;
+
+        if let Some(handle) = &self.handle {
+            handle.reload(filter).map_err(|e| e.to_string())?;
+        }
+        *self.directive.write().unwrap() = directive.to_string();
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct LoggerConfig {
     pub level: Option<String>,
     pub log_directory: Option<String>,
     pub file_prefix: Option<String>,
+    pub format: Option<LogFormat>,
+    /// 除了 `{prefix}_all.log` 之外，是否再依層級分檔
+    pub split_by_level: Option<bool>,
+    pub rotation: Option<LogRotation>,
+    /// `rotation = "size"` 時單一檔案的上限
+    pub max_file_size_mb: Option<u64>,
+    /// 每個檔案保留的輪替檔數量，0 表示不刪除
+    pub max_files: Option<usize>,
+    /// 輪替後以 gzip 壓縮
+    pub compress: Option<bool>,
+    pub otlp: Option<OtlpConfig>,
+}
+
+/// 結束時依序 flush：先把 trace 送出，再等 log 檔寫完
+pub struct LogGuards {
+    tracer: Option<SdkTracerProvider>,
+    _files: Vec<WorkerGuard>,
+}
+
+impl Drop for LogGuards {
+    fn drop(&mut self) {
+        if let Some(provider) = self.tracer.take()
+            && let Err(err) = provider.shutdown()
+        {
+            eprintln!("Failed to flush traces: {}", err);
+        }
+    }
 }
 
 impl LoggerConfig {
-    pub fn load(&self) -> Vec<WorkerGuard> {
+    /// 未指定時正式環境用 compact，其他用 pretty
+    pub fn format_for(&self, profile: Profile) -> LogFormat {
+        self.format.unwrap_or(if profile.is_production() {
+            LogFormat::Compact
+        } else {
+            LogFormat::Pretty
+        })
+    }
+
+    pub fn level(&self) -> &str {
+        self.level.as_deref().unwrap_or("info")
+    }
+
+    /// 未指定時保留每個層級各自的檔案
+    pub fn split_by_level(&self) -> bool {
+        self.split_by_level.unwrap_or(true)
+    }
+
+    pub fn roll_policy(&self) -> RollPolicy {
+        match self.rotation.unwrap_or_default() {
+            LogRotation::Hourly => RollPolicy::Hourly,
+            LogRotation::Daily => RollPolicy::Daily,
+            LogRotation::Size => {
+                // builder 不經過設定檢查，過大的值停在上限而不溢位
+                RollPolicy::Size(
+                    self.max_file_size_mb
+                        .unwrap_or(100)
+                        .saturating_mul(1024 * 1024),
+                )
+            }
+        }
+    }
+
+    pub fn max_files(&self) -> Option<usize> {
+        match self.max_files.unwrap_or(72) {
+            0 => None,
+            max_files => Some(max_files),
+        }
+    }
+
+    pub fn compress(&self) -> bool {
+        self.compress.unwrap_or(false)
+    }
+
+    /// 安裝全域 subscriber，回傳 appender 與 trace 的 guard 及可在執行期間更換的 log level
+    pub fn load(&self, profile: Profile) -> (LogGuards, LogLevel) {
         let mut guards = Vec::new();
 
-        let level = self.level.as_deref().unwrap_or("info");
+        let level = self.level();
+        let format = self.format_for(profile);
         let log_directory = self.log_directory.as_deref().unwrap_or("/tmp");
         let file_prefix = self.file_prefix.as_deref().unwrap_or("app");
 
+        let mut layers = vec![Self::console_layer(format)];
+
         // All Levels
-        let (nb_all, g_all) = Self::make_appender(log_directory, file_prefix, "all");
+        let (nb_all, g_all) = self.make_appender(log_directory, file_prefix, "all");
         guards.push(g_all);
+        layers.push(Self::file_layer(format, nb_all));
 
-        // Trace
-        let (nb_trace, g_trace) = Self::make_appender(log_directory, file_prefix, "trace");
-        guards.push(g_trace);
-
-        // Debug
-        let (nb_debug, g_debug) = Self::make_appender(log_directory, file_prefix, "debug");
-        guards.push(g_debug);
-
-        // Info
-        let (nb_info, g_info) = Self::make_appender(log_directory, file_prefix, "info");
-        guards.push(g_info);
+        // 每個層級各一個檔案
+        if self.split_by_level() {
+            for level in [
+                LevelFilter::TRACE,
+                LevelFilter::DEBUG,
+                LevelFilter::INFO,
+                LevelFilter::WARN,
+                LevelFilter::ERROR,
+            ] {
+                let suffix = level.to_string().to_lowercase();
+                let (nb, guard) = self.make_appender(log_directory, file_prefix, &suffix);
+                guards.push(guard);
 
-        // Warn
-        let (nb_warn, g_warn) = Self::make_appender(log_directory, file_prefix, "warn");
-        guards.push(g_warn);
+                let level_only = tracing_subscriber::filter::filter_fn(move |metadata| {
+                    LevelFilter::from_level(*metadata.level()) == level
+                });
+                layers.push(Self::file_layer(format, nb).with_filter(level_only).boxed());
+            }
+        }
 
-        // Error
-        let (nb_error, g_error) = Self::make_appender(log_directory, file_prefix, "error");
-        guards.push(g_error);
+        // OTLP trace
+        let otlp = self.otlp.clone().unwrap_or_default();
+        let tracer = otlp.tracer_provider();
+        if let Some(provider) = &tracer {
+            layers.push(telemetry::otel_layer(provider, otlp.service_name()));
+        }
 
         // golbal level
         let level_filter =
             EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level));
+        let directive = level_filter.to_string();
+        let (level_filter, handle) = reload::Layer::new(level_filter);
 
         // 集合
         let subscriber = tracing_subscriber::registry()
             .with(level_filter)
-            .with(Self::console_layer(LevelFilter::TRACE))
-            .with(Self::file_level(nb_all, LevelFilter::TRACE))
-            .with(Self::file_level_only(nb_trace, LevelFilter::TRACE))
-            .with(Self::file_level_only(nb_debug, LevelFilter::DEBUG))
-            .with(Self::file_level_only(nb_info, LevelFilter::INFO))
-            .with(Self::file_level_only(nb_warn, LevelFilter::WARN))
-            .with(Self::file_level_only(nb_error, LevelFilter::ERROR));
+            .with(layers);
 
         tracing::subscriber::set_global_default(subscriber)
             .expect("setting default subscriber failed");
 
-        guards
+        (
+            LogGuards {
+                tracer,
+                _files: guards,
+            },
+            LogLevel {
+                directive: Arc::new(RwLock::new(directive)),
+                handle: Some(handle),
+            },
+        )
     }
 
-    fn make_appender(dir: &str, prefix: &str, suffix: &str) -> (NonBlocking, WorkerGuard) {
-        let file = tracing_appender::rolling::hourly(dir, format!("{}_{}.log", prefix, suffix));
+    fn make_appender(&self, dir: &str, prefix: &str, suffix: &str) -> (NonBlocking, WorkerGuard) {
+        let file = RollingFile::new(
+            dir,
+            &format!("{}_{}.log", prefix, suffix),
+            self.roll_policy(),
+            self.max_files(),
+            self.compress(),
+        )
+        .expect("Failed to open log file");
         tracing_appender::non_blocking(file)
     }
 
-    /// Console layer（可指定 Level）
-    fn console_layer<S>(level: LevelFilter) -> impl Layer<S> + Send + Sync
+    /// 每個事件一行 JSON，附上目前 span 與所有上層 span 的欄位（method、uri、request_id、sku、order_id）
+    pub fn json_layer<S, W>(writer: W) -> Box<dyn Layer<S> + Send + Sync>
     where
         S: Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+        W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
     {
         tracing_subscriber::fmt::layer()
-            .with_ansi(true)
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(true)
             .with_level(true)
-            .with_file(true)
-            .with_line_number(true)
-            .with_target(false)
-            .with_thread_ids(true)
+            .with_target(true)
             .with_timer(LocalTimer)
-            .with_filter(level)
+            .with_writer(writer)
+            .boxed()
     }
 
-    fn file_level<S>(block: NonBlocking, level: LevelFilter) -> impl Layer<S> + Send + Sync
+    /// Console layer
+    fn console_layer<S>(format: LogFormat) -> Box<dyn Layer<S> + Send + Sync>
     where
         S: Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
     {
-        tracing_subscriber::fmt::layer()
-            .with_ansi(false)
-            .with_level(true)
-            .with_file(true)
-            .with_line_number(true)
-            .with_target(false)
-            .with_thread_ids(true)
-            .with_writer(block)
-            .with_timer(LocalTimer)
-            .with_filter(level)
+        match format {
+            LogFormat::Pretty => tracing_subscriber::fmt::layer()
+                .with_ansi(true)
+                .with_level(true)
+                .with_file(true)
+                .with_line_number(true)
+                .with_target(false)
+                .with_thread_ids(true)
+                .with_timer(LocalTimer)
+                .boxed(),
+            LogFormat::Compact => tracing_subscriber::fmt::layer()
+                .compact()
+                .with_ansi(false)
+                .with_level(true)
+                .with_target(true)
+                .with_timer(LocalTimer)
+                .boxed(),
+            LogFormat::Json => Self::json_layer(std::io::stdout),
+        }
     }
 
-    fn file_level_only<S>(block: NonBlocking, level: LevelFilter) -> impl Layer<S> + Send + Sync
+    /// 檔案沿用 console 的 JSON 格式，其他格式一律寫成無色碼的文字
+    fn file_layer<S>(format: LogFormat, block: NonBlocking) -> Box<dyn Layer<S> + Send + Sync>
     where
         S: Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
     {
-        let level_only =
-            tracing_subscriber::filter::filter_fn(move |metadata| metadata.level() == &level);
-
-        tracing_subscriber::fmt::layer()
-            .with_ansi(false)
-            .with_level(true)
-            .with_file(true)
-            .with_line_number(true)
-            .with_target(false)
-            .with_thread_ids(true)
-            .with_writer(block)
-            .with_timer(LocalTimer)
-            .with_filter(level_only)
+        match format {
+            LogFormat::Json => Self::json_layer(block),
+            LogFormat::Pretty | LogFormat::Compact => tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_level(true)
+                .with_file(true)
+                .with_line_number(true)
+                .with_target(false)
+                .with_thread_ids(true)
+                .with_writer(block)
+                .with_timer(LocalTimer)
+                .boxed(),
+        }
     }
 }
@@ -0,0 +1,22 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MessageBusConfig {
+    pub max_retries: Option<u32>,
+    pub retry_backoff_ms: Option<u64>,
+}
+
+impl MessageBusConfig {
+    /// 版本衝突時重試整個命令的次數上限
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries.unwrap_or(3)
+    }
+
+    /// 第 n 次重試前的等待時間，逐次加倍
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let base = self.retry_backoff_ms.unwrap_or(10);
+        Duration::from_millis(base.saturating_mul(1 << attempt.min(10)))
+    }
+}
@@ -1,48 +1,278 @@
+mod allocation;
+mod arrival;
+mod auth;
 mod database;
 mod logger;
+mod messagebus;
+mod reservation;
+mod rolling;
+mod runtime;
+mod secret;
 mod server;
+mod telemetry;
+mod validation;
+mod warehouse;
 
-use std::sync::LazyLock;
+use std::path::{Path, PathBuf};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::configures::database::DatabaseConfig;
-use crate::configures::logger::LoggerConfig;
-use crate::configures::server::ServerConfig;
+pub use crate::configures::allocation::AllocationConfig;
+pub use crate::configures::arrival::ArrivalConfig;
+pub use crate::configures::auth::{AuthConfig, Role, UserConfig};
+pub use crate::configures::database::{DatabaseConfig, DatabaseDriver, JournalMode, Synchronous};
+pub use crate::configures::logger::{LogFormat, LogGuards, LogLevel, LoggerConfig};
+pub use crate::configures::messagebus::MessageBusConfig;
+pub use crate::configures::reservation::ReservationConfig;
+pub use crate::configures::rolling::{LogRotation, RollPolicy, RollingFile};
+pub use crate::configures::runtime::RuntimeConfig;
+pub use crate::configures::secret::SecretConfig;
+pub use crate::configures::server::{Profile, ServerConfig};
+pub use crate::configures::telemetry::{OtlpConfig, otel_layer};
+pub use crate::configures::validation::{ConfigError, ConfigIssue, ConfigSource};
+pub use crate::configures::warehouse::WarehouseConfig;
 
-static CONFIG_FILE: LazyLock<AppConfig> = LazyLock::new(|| AppConfig::load());
+/// 預設的設定檔，省略副檔名
+pub const CONFIG_FILE: &str = "Configure";
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AppConfig {
+    #[serde(default)]
     pub server: ServerConfig,
+    #[serde(default)]
     pub database: DatabaseConfig,
+    #[serde(default)]
     pub logger: LoggerConfig,
+    #[serde(default)]
+    pub allocation: AllocationConfig,
+    #[serde(default)]
+    pub reservation: ReservationConfig,
+    #[serde(default)]
+    pub warehouse: WarehouseConfig,
+    #[serde(default)]
+    pub arrival: ArrivalConfig,
+    #[serde(default)]
+    pub messagebus: MessageBusConfig,
+    #[serde(default)]
+    pub secret: SecretConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
 }
 
 impl AppConfig {
-    pub fn load() -> Self {
-        config::Config::builder()
-            // Add in `./Configures.toml`
-            .add_source(
-                config::File::with_name("Configure")
+    /// 以程式建立設定，未指定的區段使用預設值，不需要設定檔
+    pub fn builder() -> AppConfigBuilder {
+        AppConfigBuilder::default()
+    }
+
+    /// 讀取目前目錄的 `Configure.toml`
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::load_from(CONFIG_FILE)
+    }
+
+    /// 會影響這份設定的檔案：基底設定檔與 env 對應的疊加檔
+    pub fn files(path: impl AsRef<Path>, env: &str) -> Vec<PathBuf> {
+        let name = path.as_ref().to_string_lossy();
+        let stem = name.strip_suffix(".toml").unwrap_or(&name);
+        vec![
+            PathBuf::from(format!("{}.toml", stem)),
+            PathBuf::from(format!("{}.{}.toml", stem, env)),
+        ]
+    }
+
+    /// 依序疊加：指定的設定檔、`{檔名}.{env}.toml`、`APP_` 開頭的環境變數，合併後先驗證再轉成設定
+    ///
+    /// env 取自 `APP_SERVER_ENV`，沒有時用基底設定檔的 `server.env`
+    pub fn load_from(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let base = path.to_string_lossy();
+
+        let overlay = Self::sources(&base, None)
+            .build()?
+            .get_string("server.env")
+            .ok()
+            .filter(|env| env.parse::<Profile>().is_ok())
+            .map(|env| overlay_path(path, &env));
+        let merged = Self::sources(&base, overlay.as_deref()).build()?;
+
+        let schema = serde_json::to_value(Self::default()).unwrap();
+        let issues = validation::validate(&merged.cache, &schema, &base);
+        if !issues.is_empty() {
+            return Err(ConfigError::Invalid(issues));
+        }
+
+        Ok(merged.try_deserialize()?)
+    }
+
+    fn sources(
+        base: &str,
+        overlay: Option<&Path>,
+    ) -> config::ConfigBuilder<config::builder::DefaultState> {
+        let mut builder = config::Config::builder().add_source(
+            config::File::with_name(base)
+                .format(config::FileFormat::Toml)
+                .required(true),
+        );
+        if let Some(overlay) = overlay {
+            builder = builder.add_source(
+                config::File::with_name(&overlay.to_string_lossy())
                     .format(config::FileFormat::Toml)
-                    .required(true),
-            )
-            // Add in settings from the environment (with a prefix of APP)
-            // Eg.. `APP_DEBUG=1 ./target/app` would set the `debug` key
-            .add_source(
-                config::Environment::with_prefix("APP")
-                    .try_parsing(true)
-                    .separator("_")
-                    .list_separator(","),
-            )
-            .build()
-            .unwrap()
-            .try_deserialize()
-            .unwrap()
+                    .required(false),
+            );
+        }
+        // Add in settings from the environment (with a prefix of APP)
+        // Eg.. `APP_DATABASE_MAX_CONNECTIONS=3 ./target/app` would set `database.max_connections`
+        builder.add_source(Self::environment())
+    }
+
+    /// 只有倉庫偏好是清單（`APP_WAREHOUSE_REGIONS_NORTH=TPE,TXG`），其他值即使含逗號也維持字串
+    fn environment() -> config::Environment {
+        let schema = serde_json::to_value(Self::default()).unwrap();
+        // key 本身含底線（`max_connections`），先依設定的結構找出 key，再以 `__` 分隔交給 config
+        let vars: Vec<(String, String)> = std::env::vars()
+            .filter_map(|(name, value)| {
+                let key = env_key(&schema, &name.strip_prefix("APP_")?.to_lowercase())?;
+                Some((key, value))
+            })
+            .collect();
+
+        let environment = config::Environment::with_prefix("APP")
+            .prefix_separator("_")
+            .separator("__")
+            .try_parsing(true)
+            .list_separator(",")
+            .with_list_parse_key("warehouse.regions");
+
+        let environment = vars
+            .iter()
+            .filter(|(key, _)| key.starts_with("warehouse.regions."))
+            .fold(environment, |environment, (key, _)| {
+                environment.with_list_parse_key(key)
+            });
+
+        let vars = vars
+            .into_iter()
+            .map(|(key, value)| (format!("APP_{}", key.replace('.', "__")), value))
+            .collect();
+        environment.source(Some(vars))
+    }
+
+    /// 輸出合併後的設定，密碼與金鑰以 `******` 遮蔽
+    pub fn redacted(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap();
+        for pointer in SECRETS {
+            if let Some(secret) = value.pointer_mut(pointer).filter(|v| !v.is_null()) {
+                *secret = serde_json::Value::from("******");
+            }
+        }
+        if let Some(users) = value
+            .pointer_mut("/auth/users")
+            .and_then(|v| v.as_array_mut())
+        {
+            for user in users {
+                user["password_hash"] = serde_json::Value::from("******");
+            }
+        }
+        value
+    }
+}
+
+/// 依設定的結構將環境變數名稱（去掉 `APP_`、轉小寫）對應到 key，例如 `database_max_connections`
+/// 對應 `database.max_connections`；結構中沒有的變數（`APP_HOME`）回傳 None 並略過。
+/// 選填的區段與自訂 map 在結構中是 null，剩下的名稱整段當作下一層的 key
+fn env_key(schema: &serde_json::Value, name: &str) -> Option<String> {
+    let serde_json::Value::Object(fields) = schema else {
+        return (!name.is_empty()).then(|| name.to_string());
+    };
+
+    let mut keys: Vec<&String> = fields.keys().collect();
+    keys.sort_by_key(|key| std::cmp::Reverse(key.len()));
+    keys.into_iter().find_map(|key| {
+        let child = &fields[key];
+        if name == key {
+            return (!child.is_object()).then(|| key.clone());
+        }
+        let rest = name.strip_prefix(key.as_str())?.strip_prefix('_')?;
+        if child.is_object() || child.is_null() {
+            env_key(child, rest).map(|rest| format!("{}.{}", key, rest))
+        } else {
+            None
+        }
+    })
+}
+
+/// `Configure` 對應 `Configure.production`，`dir/app.toml` 對應 `dir/app.production.toml`
+fn overlay_path(path: &Path, env: &str) -> PathBuf {
+    let name = path.to_string_lossy();
+    match name.strip_suffix(".toml") {
+        Some(stem) => PathBuf::from(format!("{}.{}.toml", stem, env)),
+        None => PathBuf::from(format!("{}.{}", name, env)),
     }
 }
 
-pub fn get_config() -> &'static AppConfig {
-    &CONFIG_FILE
+const SECRETS: &[&str] = &[
+    "/database/password",
+    "/secret/jwt_secret",
+    "/secret/refresh_secret",
+];
+
+#[derive(Debug, Default)]
+pub struct AppConfigBuilder {
+    config: AppConfig,
+}
+
+impl AppConfigBuilder {
+    pub fn server(mut self, server: ServerConfig) -> Self {
+        self.config.server = server;
+        self
+    }
+
+    pub fn database(mut self, database: DatabaseConfig) -> Self {
+        self.config.database = database;
+        self
+    }
+
+    pub fn logger(mut self, logger: LoggerConfig) -> Self {
+        self.config.logger = logger;
+        self
+    }
+
+    pub fn allocation(mut self, allocation: AllocationConfig) -> Self {
+        self.config.allocation = allocation;
+        self
+    }
+
+    pub fn reservation(mut self, reservation: ReservationConfig) -> Self {
+        self.config.reservation = reservation;
+        self
+    }
+
+    pub fn warehouse(mut self, warehouse: WarehouseConfig) -> Self {
+        self.config.warehouse = warehouse;
+        self
+    }
+
+    pub fn arrival(mut self, arrival: ArrivalConfig) -> Self {
+        self.config.arrival = arrival;
+        self
+    }
+
+    pub fn messagebus(mut self, messagebus: MessageBusConfig) -> Self {
+        self.config.messagebus = messagebus;
+        self
+    }
+
+    pub fn secret(mut self, secret: SecretConfig) -> Self {
+        self.config.secret = secret;
+        self
+    }
+
+    pub fn auth(mut self, auth: AuthConfig) -> Self {
+        self.config.auth = auth;
+        self
+    }
+
+    pub fn build(self) -> AppConfig {
+        self.config
+    }
 }
@@ -0,0 +1,23 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReservationConfig {
+    pub ttl_seconds: Option<u64>,
+    pub sweep_interval_seconds: Option<u64>,
+}
+
+impl ReservationConfig {
+    /// 保留的預設有效時間；秒數超出可表示的範圍時為 None
+    pub fn ttl(&self) -> Option<chrono::Duration> {
+        i64::try_from(self.ttl_seconds.unwrap_or(300))
+            .ok()
+            .and_then(chrono::Duration::try_seconds)
+    }
+
+    /// sweeper 檢查到期保留的間隔
+    pub fn sweep_interval(&self) -> Duration {
+        Duration::from_secs(self.sweep_interval_seconds.unwrap_or(30).max(1))
+    }
+}
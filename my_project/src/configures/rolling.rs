@@ -0,0 +1,225 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::thread::JoinHandle;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Local};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+
+/// log 檔的輪替方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogRotation {
+    #[default]
+    Hourly,
+    Daily,
+    /// 超過 `logger.max_file_size_mb` 即輪替
+    Size,
+}
+
+impl LogRotation {
+    pub const NAMES: &'static [&'static str] = &["hourly", "daily", "size"];
+}
+
+/// 實際的輪替條件，Size 以 byte 計
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RollPolicy {
+    Hourly,
+    Daily,
+    Size(u64),
+}
+
+impl RollPolicy {
+    fn period(&self, time: DateTime<Local>) -> Option<String> {
+        match self {
+            RollPolicy::Hourly => Some(time.format("%Y-%m-%d-%H").to_string()),
+            RollPolicy::Daily => Some(time.format("%Y-%m-%d").to_string()),
+            RollPolicy::Size(_) => None,
+        }
+    }
+}
+
+/// 寫入 `{dir}/{name}`，輪替時改名為 `{name}.{時間}`（可再壓成 `.gz`），只保留最新的 `max_files` 個
+pub struct RollingFile {
+    dir: PathBuf,
+    name: String,
+    policy: RollPolicy,
+    max_files: Option<usize>,
+    file: File,
+    period: Option<String>,
+    written: u64,
+    /// 壓縮在另一個執行緒進行，不佔用寫 log 的執行緒
+    compressor: Option<(Sender<PathBuf>, JoinHandle<()>)>,
+}
+
+impl RollingFile {
+    pub fn new(
+        dir: impl AsRef<Path>,
+        name: &str,
+        policy: RollPolicy,
+        max_files: Option<usize>,
+        compress: bool,
+    ) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(name))?;
+        let metadata = file.metadata()?;
+        // 沿用既有檔案時以最後修改時間判斷所屬的時段，重啟後跨時段仍會輪替
+        let modified: DateTime<Local> = metadata.modified().unwrap_or(SystemTime::now()).into();
+
+        let compressor = compress.then(|| {
+            let (sender, receiver) = mpsc::channel::<PathBuf>();
+            let (dir, name) = (dir.clone(), name.to_string());
+            let handle = std::thread::spawn(move || {
+                for rotated in receiver {
+                    if let Err(err) =
+                        compress_file(&rotated).and_then(|_| prune(&dir, &name, max_files))
+                    {
+                        // 寫 log 的途徑本身出錯，只能輸出到 stderr
+                        eprintln!("Failed to compress {}: {}", rotated.display(), err);
+                    }
+                }
+            });
+            (sender, handle)
+        });
+
+        Ok(RollingFile {
+            period: policy.period(modified),
+            written: metadata.len(),
+            dir,
+            name: name.to_string(),
+            policy,
+            max_files,
+            file,
+            compressor,
+        })
+    }
+
+    fn active(&self) -> PathBuf {
+        self.dir.join(&self.name)
+    }
+
+    fn should_roll(&self, now: DateTime<Local>, incoming: usize) -> bool {
+        if self.written == 0 {
+            return false;
+        }
+        match self.policy {
+            RollPolicy::Size(max) => self.written + incoming as u64 > max,
+            _ => self.policy.period(now) != self.period,
+        }
+    }
+
+    fn roll(&mut self, now: DateTime<Local>) -> io::Result<()> {
+        self.file.flush()?;
+
+        let stamp = self
+            .period
+            .clone()
+            .unwrap_or_else(|| now.format("%Y-%m-%d-%H%M%S%.3f").to_string());
+        let mut rotated = self.dir.join(format!("{}.{}", self.name, stamp));
+        let mut n = 1;
+        while rotated.exists() || gz_path(&rotated).exists() {
+            rotated = self.dir.join(format!("{}.{}.{}", self.name, stamp, n));
+            n += 1;
+        }
+
+        fs::rename(self.active(), &rotated)?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.active())?;
+        self.period = self.policy.period(now);
+        self.written = 0;
+
+        match &self.compressor {
+            // 壓縮完才清除舊檔，交給壓縮執行緒依序處理
+            Some((sender, _)) => {
+                let _ = sender.send(rotated);
+                Ok(())
+            }
+            None => prune(&self.dir, &self.name, self.max_files),
+        }
+    }
+}
+
+/// 結束前等待排隊中的壓縮完成
+impl Drop for RollingFile {
+    fn drop(&mut self) {
+        if let Some((sender, handle)) = self.compressor.take() {
+            drop(sender);
+            let _ = handle.join();
+        }
+    }
+}
+
+/// 依修改時間刪除超過保留數量的舊檔
+fn prune(dir: &Path, name: &str, max_files: Option<usize>) -> io::Result<()> {
+    let Some(max_files) = max_files else {
+        return Ok(());
+    };
+
+    let prefix = format!("{}.", name);
+    let mut rotated: Vec<(SystemTime, PathBuf)> = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .filter_map(|entry| {
+            let modified = entry.metadata().and_then(|m| m.modified()).ok()?;
+            Some((modified, entry.path()))
+        })
+        .collect();
+    rotated.sort();
+
+    let excess = rotated.len().saturating_sub(max_files);
+    for (_, path) in rotated.into_iter().take(excess) {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+impl Write for RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let now = Local::now();
+        if self.should_roll(now, buf.len()) {
+            self.roll(now)?;
+        } else if self.written == 0 {
+            self.period = self.policy.period(now);
+        }
+
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn gz_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".gz");
+    PathBuf::from(name)
+}
+
+/// 壓縮檔沿用原檔的修改時間，清除舊檔時的先後順序不受壓縮時間影響
+fn compress_file(path: &Path) -> io::Result<()> {
+    let mut input = match File::open(path) {
+        Ok(input) => input,
+        // 排隊期間已被清除
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    let modified = input.metadata()?.modified()?;
+    let mut encoder = GzEncoder::new(File::create(gz_path(path))?, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.set_modified(modified)?;
+    fs::remove_file(path)
+}
@@ -0,0 +1,60 @@
+use std::sync::{Arc, RwLock};
+
+use crate::configures::{AppConfig, LogLevel, ServerConfig};
+
+/// 執行期間可重新載入的設定：log level、CORS 白名單與 request timeout
+#[derive(Clone)]
+pub struct RuntimeConfig {
+    server: Arc<RwLock<ServerConfig>>,
+    file_level: Arc<RwLock<String>>,
+    log_level: LogLevel,
+}
+
+impl RuntimeConfig {
+    pub fn new(config: &AppConfig, log_level: LogLevel) -> Self {
+        RuntimeConfig {
+            server: Arc::new(RwLock::new(config.server.clone())),
+            file_level: Arc::new(RwLock::new(config.logger.level().to_string())),
+            log_level,
+        }
+    }
+
+    /// 測試用：不安裝全域 subscriber
+    pub fn detached(config: &AppConfig) -> Self {
+        Self::new(config, LogLevel::detached(config.logger.level()))
+    }
+
+    pub fn server(&self) -> ServerConfig {
+        self.server.read().unwrap().clone()
+    }
+
+    pub fn log_level(&self) -> &LogLevel {
+        &self.log_level
+    }
+
+    /// 套用重新讀取的設定，回傳有變更的 key；其他設定需要重新啟動才會生效
+    ///
+    /// log level 只在設定檔的值改變時才套用，不覆蓋 `PUT /admin/log-level` 的調整
+    pub fn apply(&self, config: &AppConfig) -> Result<Vec<&'static str>, String> {
+        let mut changed = Vec::new();
+
+        let level = config.logger.level();
+        if *self.file_level.read().unwrap() != level {
+            self.log_level.set(level)?;
+            *self.file_level.write().unwrap() = level.to_string();
+            changed.push("logger.level");
+        }
+
+        let mut server = self.server.write().unwrap();
+        if server.cors_origins != config.server.cors_origins {
+            server.cors_origins = config.server.cors_origins.clone();
+            changed.push("server.cors_origins");
+        }
+        if server.request_timeout_seconds != config.server.request_timeout_seconds {
+            server.request_timeout_seconds = config.server.request_timeout_seconds;
+            changed.push("server.request_timeout_seconds");
+        }
+
+        Ok(changed)
+    }
+}
@@ -0,0 +1,7 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SecretConfig {
+    pub jwt_secret: Option<String>,
+    pub refresh_secret: Option<String>,
+}
@@ -1,10 +1,46 @@
+use std::str::FromStr;
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+/// 執行環境，決定各項設定的預設行為
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    Development,
+    Test,
+    Production,
+}
+
+impl Profile {
+    pub const NAMES: &'static [&'static str] = &["development", "test", "production"];
+
+    /// 正式環境：CORS 只允許白名單、不自動跑 migration
+    pub fn is_production(&self) -> bool {
+        *self == Profile::Production
+    }
+}
+
+impl FromStr for Profile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "development" => Ok(Profile::Development),
+            "test" => Ok(Profile::Test),
+            "production" => Ok(Profile::Production),
+            other => Err(format!("Unknown profile {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ServerConfig {
     pub env: Option<String>,
     pub host: Option<String>,
     pub port: Option<u16>,
+    pub cors_origins: Option<Vec<String>>,
+    pub request_timeout_seconds: Option<u64>,
+    pub reload_interval_seconds: Option<u64>,
 }
 
 impl ServerConfig {
@@ -12,6 +48,11 @@ impl ServerConfig {
         self.env.as_deref().unwrap_or("development").to_string()
     }
 
+    /// 無法辨識的 env 在載入時就會被擋下，這裡退回 development
+    pub fn profile(&self) -> Profile {
+        self.app_env().parse().unwrap_or(Profile::Development)
+    }
+
     pub fn address(&self) -> String {
         format!(
             "{}:{}",
@@ -19,4 +60,19 @@ impl ServerConfig {
             self.port.unwrap_or(3000)
         )
     }
+
+    /// 正式環境未設定白名單時不允許任何跨域來源
+    pub fn cors_origins(&self) -> Vec<String> {
+        self.cors_origins.clone().unwrap_or_default()
+    }
+
+    /// 單一 request 的處理時限，可在執行期間重新載入
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_secs(self.request_timeout_seconds.unwrap_or(10).max(1))
+    }
+
+    /// 檢查設定檔是否變更的間隔
+    pub fn reload_interval(&self) -> Duration {
+        Duration::from_secs(self.reload_interval_seconds.unwrap_or(5).max(1))
+    }
 }
@@ -0,0 +1,59 @@
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use serde::{Deserialize, Serialize};
+use tracing::Subscriber;
+use tracing_subscriber::Layer;
+use tracing_subscriber::filter::filter_fn;
+
+/// OTLP trace 匯出，未設定 endpoint 時不啟用
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OtlpConfig {
+    /// OTLP/HTTP 的完整路徑，例如 `http://localhost:4318/v1/traces`
+    pub endpoint: Option<String>,
+    /// 未指定時為 `architecture`
+    pub service_name: Option<String>,
+}
+
+impl OtlpConfig {
+    pub fn service_name(&self) -> &str {
+        self.service_name.as_deref().unwrap_or("architecture")
+    }
+
+    /// 以批次方式在背景執行緒送往 collector；endpoint 在載入設定時已驗證
+    pub fn tracer_provider(&self) -> Option<SdkTracerProvider> {
+        let endpoint = self.endpoint.as_deref()?;
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint)
+            .build()
+            .expect("Failed to build OTLP exporter");
+
+        Some(
+            SdkTracerProvider::builder()
+                .with_batch_exporter(exporter)
+                .with_resource(
+                    Resource::builder()
+                        .with_service_name(self.service_name().to_string())
+                        .build(),
+                )
+                .build(),
+        )
+    }
+}
+
+/// 把 tracing 的 span 轉成 OpenTelemetry span；測試可傳入記憶體中的 exporter
+pub fn otel_layer<S>(
+    provider: &SdkTracerProvider,
+    service_name: &str,
+) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a> + Send + Sync,
+{
+    // sqlx 的查詢 log 帶有完整的 SQL，不隨 span 送出
+    tracing_opentelemetry::layer()
+        .with_tracer(provider.tracer(service_name.to_string()))
+        .with_filter(filter_fn(|metadata| !metadata.target().starts_with("sqlx")))
+        .boxed()
+}
@@ -0,0 +1,322 @@
+use std::fmt;
+
+use config::{Value, ValueKind};
+
+use crate::configures::{
+    DatabaseDriver, JournalMode, LogFormat, LogRotation, Profile, Role, Synchronous,
+};
+
+/// 設定值的來源
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    File(String),
+    Env(String),
+}
+
+impl ConfigSource {
+    fn of(key: &str, value: &Value) -> Self {
+        match value.origin() {
+            Some(origin) if origin != "the environment" => Self::File(origin.to_string()),
+            _ => Self::Env(env_var(key)),
+        }
+    }
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "file {}", path),
+            Self::Env(name) => write!(f, "env var {}", name),
+        }
+    }
+}
+
+/// 單一設定錯誤，附上完整的 key 路徑與來源
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    pub key: String,
+    pub source: ConfigSource,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}): {}", self.key, self.source, self.message)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("failed to load configuration: {0}")]
+    Load(#[from] config::ConfigError),
+    #[error("invalid configuration:\n{}", list(.0))]
+    Invalid(Vec<ConfigIssue>),
+}
+
+impl ConfigError {
+    pub fn issues(&self) -> &[ConfigIssue] {
+        match self {
+            Self::Invalid(issues) => issues,
+            Self::Load(_) => &[],
+        }
+    }
+}
+
+fn list(issues: &[ConfigIssue]) -> String {
+    issues
+        .iter()
+        .map(|issue| format!("  - {}", issue))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+const REQUIRED: &[&str] = &["database.database"];
+const PORTS: &[&str] = &["server.port", "database.port"];
+const POSITIVE: &[&str] = &["database.max_connections", "logger.max_file_size_mb"];
+/// 有上限的數值：過長的期限或過大的 log 檔失去意義，也避免換算時溢位
+const MAXIMUMS: &[(&str, i64)] = &[
+    ("auth.access_ttl_seconds", 24 * 3600),
+    ("auth.refresh_ttl_seconds", 365 * 24 * 3600),
+    ("reservation.ttl_seconds", 30 * 24 * 3600),
+    ("logger.max_file_size_mb", 10 * 1024),
+];
+/// 正式環境必須提供、且不能沿用範例值的簽章金鑰
+const SECRETS: &[&str] = &["secret.jwt_secret", "secret.refresh_secret"];
+const PLACEHOLDER_SECRETS: &[&str] = &["your_jwt_secret_here", "your_refresh_secret_here"];
+const MIN_SECRET_LEN: usize = 32;
+const LEVELS: &[&str] = &["trace", "debug", "info", "warn", "error", "off"];
+const CHOICES: &[(&str, &[&str])] = &[
+    ("database.driver", DatabaseDriver::NAMES),
+    ("database.journal_mode", JournalMode::NAMES),
+    ("database.synchronous", Synchronous::NAMES),
+    ("logger.format", LogFormat::NAMES),
+    ("logger.rotation", LogRotation::NAMES),
+];
+
+/// 檢查合併後的設定：未知的 key、缺少的必填值、不合法的 port、選項、連線池與 log level
+pub fn validate(merged: &Value, schema: &serde_json::Value, file: &str) -> Vec<ConfigIssue> {
+    let mut issues = Vec::new();
+    unknown_keys(merged, schema, "", &mut issues);
+
+    for key in REQUIRED {
+        let missing = match lookup(merged, key) {
+            None => true,
+            Some(value) => value
+                .clone()
+                .into_string()
+                .is_ok_and(|s| s.trim().is_empty()),
+        };
+        if missing {
+            issues.push(ConfigIssue {
+                key: key.to_string(),
+                source: ConfigSource::File(file.to_string()),
+                message: format!("required value is missing (or set {})", env_var(key)),
+            });
+        }
+    }
+
+    for key in PORTS {
+        if let Some(value) = lookup(merged, key) {
+            let message = match value.clone().into_int() {
+                Ok(port) if (1..=65535).contains(&port) => continue,
+                Ok(port) => format!("port must be between 1 and 65535, got {}", port),
+                Err(_) => "expected a port number between 1 and 65535".to_string(),
+            };
+            issues.push(issue(key, value, message));
+        }
+    }
+
+    for (key, names) in CHOICES {
+        if let Some(value) = lookup(merged, key) {
+            let choice = value.to_string();
+            if !names.contains(&choice.as_str()) {
+                issues.push(issue(
+                    key,
+                    value,
+                    format!(
+                        "unknown value `{}`, expected one of {}",
+                        choice,
+                        names.join(", ")
+                    ),
+                ));
+            }
+        }
+    }
+
+    for key in POSITIVE {
+        if let Some(value) = lookup(merged, key)
+            && value.clone().into_int().is_ok_and(|n| n < 1)
+        {
+            issues.push(issue(key, value, "must be at least 1".to_string()));
+        }
+    }
+
+    for (key, max) in MAXIMUMS {
+        if let Some(value) = lookup(merged, key)
+            && value.clone().into_int().is_ok_and(|n| n > *max)
+        {
+            issues.push(issue(key, value, format!("must be at most {}", max)));
+        }
+    }
+
+    let max = lookup(merged, "database.max_connections");
+    if let Some(value) = lookup(merged, "database.min_connections")
+        && let Ok(min) = value.clone().into_int()
+        && let Ok(max) = max.map_or(Ok(10), |max| max.clone().into_int())
+        && min > max
+    {
+        issues.push(issue(
+            "database.min_connections",
+            value,
+            format!("must not exceed max_connections ({})", max),
+        ));
+    }
+
+    if let Some(value) = lookup(merged, "server.env") {
+        let env = value.to_string();
+        if env.parse::<Profile>().is_err() {
+            issues.push(issue(
+                "server.env",
+                value,
+                format!(
+                    "unknown profile `{}`, expected one of {}",
+                    env,
+                    Profile::NAMES.join(", ")
+                ),
+            ));
+        }
+    }
+
+    let production = lookup(merged, "server.env")
+        .and_then(|value| value.to_string().parse::<Profile>().ok())
+        .is_some_and(|profile| profile.is_production());
+    if production {
+        for key in SECRETS {
+            let secret = lookup(merged, key).map(|value| (value, value.to_string()));
+            let message = match &secret {
+                None => format!("required in production (set {})", env_var(key)),
+                Some((_, secret)) if secret.trim().is_empty() => {
+                    format!("required in production (set {})", env_var(key))
+                }
+                Some((_, secret)) if PLACEHOLDER_SECRETS.contains(&secret.as_str()) => {
+                    "placeholder secret must be replaced in production".to_string()
+                }
+                Some((_, secret)) if secret.len() < MIN_SECRET_LEN => {
+                    format!(
+                        "must be at least {} characters in production",
+                        MIN_SECRET_LEN
+                    )
+                }
+                Some(_) => continue,
+            };
+            issues.push(match secret {
+                Some((value, _)) => issue(key, value, message),
+                None => ConfigIssue {
+                    key: key.to_string(),
+                    source: ConfigSource::File(file.to_string()),
+                    message,
+                },
+            });
+        }
+    }
+
+    if let Some(ValueKind::Array(users)) = lookup(merged, "auth.users").map(|v| &v.kind) {
+        for (i, user) in users.iter().enumerate() {
+            let key = format!("auth.users[{}].roles", i);
+            let Some(ValueKind::Array(roles)) = lookup(user, "roles").map(|v| &v.kind) else {
+                continue;
+            };
+            for role in roles {
+                if let Err(message) = role.to_string().parse::<Role>() {
+                    issues.push(issue(&key, role, message));
+                }
+            }
+        }
+    }
+
+    if let Some(value) = lookup(merged, "logger.level") {
+        let level = value.to_string();
+        if let Some(directive) = invalid_directive(&level) {
+            issues.push(issue(
+                "logger.level",
+                value,
+                format!(
+                    "invalid log level `{}`, expected one of {}",
+                    directive,
+                    LEVELS.join(", ")
+                ),
+            ));
+        }
+    }
+
+    if let Some(value) = lookup(merged, "logger.otlp.endpoint") {
+        let endpoint = value.to_string();
+        let valid = endpoint.parse::<axum::http::Uri>().is_ok_and(|uri| {
+            matches!(uri.scheme_str(), Some("http" | "https")) && uri.host().is_some()
+        });
+        if !valid {
+            issues.push(issue(
+                "logger.otlp.endpoint",
+                value,
+                format!("invalid endpoint `{}`, expected an http(s) URL", endpoint),
+            ));
+        }
+    }
+
+    issues
+}
+
+/// 回傳第一個層級不合法的 directive，`target=level` 只檢查等號後的層級
+pub fn invalid_directive(level: &str) -> Option<&str> {
+    level.split(',').map(str::trim).find(|directive| {
+        let name = directive.rsplit('=').next().unwrap_or_default();
+        !LEVELS.contains(&name.to_ascii_lowercase().as_str())
+    })
+}
+
+/// schema 為 null 的節點（選填值或自訂 map）不再往下檢查
+fn unknown_keys(
+    value: &Value,
+    schema: &serde_json::Value,
+    path: &str,
+    issues: &mut Vec<ConfigIssue>,
+) {
+    let (ValueKind::Table(table), serde_json::Value::Object(known)) = (&value.kind, schema) else {
+        return;
+    };
+
+    let mut keys: Vec<_> = table.keys().collect();
+    keys.sort();
+    for key in keys {
+        let child = &table[key];
+        let full = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", path, key)
+        };
+        match known.get(key) {
+            Some(schema) => unknown_keys(child, schema, &full, issues),
+            None => issues.push(issue(&full, child, "unknown key".to_string())),
+        }
+    }
+}
+
+fn lookup<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.')
+        .try_fold(value, |node, part| match &node.kind {
+            ValueKind::Table(table) => table.get(part),
+            _ => None,
+        })
+}
+
+fn issue(key: &str, value: &Value, message: String) -> ConfigIssue {
+    ConfigIssue {
+        key: key.to_string(),
+        source: ConfigSource::of(key, value),
+        message,
+    }
+}
+
+fn env_var(key: &str) -> String {
+    format!("APP_{}", key.replace('.', "_").to_uppercase())
+}
@@ -0,0 +1,18 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WarehouseConfig {
+    /// 目的地區域 -> 倉庫清單（由近到遠）
+    pub regions: Option<HashMap<String, Vec<String>>>,
+}
+
+impl WarehouseConfig {
+    /// 區域的倉庫偏好順序，未設定的區域回傳 None
+    pub fn preference_for(&self, region: &str) -> Option<Vec<String>> {
+        self.regions
+            .as_ref()
+            .and_then(|regions| regions.get(region).cloned())
+    }
+}
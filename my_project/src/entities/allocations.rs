@@ -8,6 +8,7 @@ pub struct Allocation {
     pub id: String,
     pub batch_id: String,
     pub order_line_id: String,
+    pub qty: Option<i32>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
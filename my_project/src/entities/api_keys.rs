@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+use sql_derives::SqlTable;
+
+use crate::configures::Role;
+
+/// 服務間呼叫用的 API key，只保存金鑰的 SHA-256，明文只在簽發時回傳一次
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, SqlTable, sqlx::FromRow)]
+pub struct ApiKey {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    /// 以逗號分隔的角色，例如 `sales,inventory`
+    pub scopes: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    /// 無法辨識的角色直接略過，不會多給權限
+    pub fn roles(&self) -> Vec<Role> {
+        self.scopes
+            .split(',')
+            .filter_map(|scope| scope.trim().parse().ok())
+            .collect()
+    }
+}
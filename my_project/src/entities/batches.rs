@@ -8,8 +8,11 @@ pub struct Batch {
     pub id: String,
     pub reference: String,
     pub sku: String,
+    #[sqlx(try_from = "i32")]
     pub qty: u32,
     pub eta: Option<DateTime<Utc>>,
+    pub warehouse: Option<String>,
+    pub arrived_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -17,18 +20,23 @@ pub struct Batch {
 impl Batch {
     pub fn build(&self) -> chapter1::Batch {
         chapter1::Batch::new(&self.reference, &self.sku, self.qty, self.eta)
+            .with_created_at(self.created_at)
+            .with_warehouse(self.warehouse.clone())
+    }
+
+    /// 以已分配的訂單明細重建 domain Batch
+    pub fn build_with_lines(&self, line_ents: &[order_lines::OrderLine]) -> chapter1::Batch {
+        let mut batch = self.build();
+        for line_ent in line_ents {
+            batch.allocate(&line_ent.build());
+        }
+        batch
     }
 
     pub fn allocate(&self, line_ent: order_lines::OrderLine) -> chapter1::Batch {
         let mut batch = chapter1::Batch::new(&self.reference, &self.sku, self.qty, self.eta);
 
-        let line = chapter1::OrderLine {
-            order_id: line_ent.id.clone(),
-            sku: line_ent.sku.clone(),
-            qty: line_ent.qty,
-        };
-
-        batch.allocate(&line);
+        batch.allocate(&line_ent.build());
         batch
     }
 }
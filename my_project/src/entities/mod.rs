@@ -1,4 +1,6 @@
 pub mod allocations;
+pub mod api_keys;
 pub mod batches;
 pub mod order_lines;
 pub mod products;
+pub mod reservations;
@@ -6,7 +6,9 @@ use crate::chapter1;
 #[derive(Clone, serde::Serialize, serde::Deserialize, SqlTable, sqlx::FromRow)]
 pub struct OrderLine {
     pub id: String,
+    pub order_id: Option<String>,
     pub sku: String,
+    #[sqlx(try_from = "i32")]
     pub qty: u32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -15,7 +17,7 @@ pub struct OrderLine {
 impl OrderLine {
     pub fn build(&self) -> chapter1::OrderLine {
         chapter1::OrderLine {
-            order_id: self.id.clone(),
+            order_id: self.order_id.clone().unwrap_or_else(|| self.id.clone()),
             sku: self.sku.clone(),
             qty: self.qty,
         }
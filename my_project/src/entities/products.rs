@@ -17,7 +17,8 @@ impl Product {
         chapter1::Product {
             sku: self.sku.clone(),
             version_number: self.version_number + 1,
-            batches: batches,
+            batches,
+            strategy: chapter1::AllocationStrategyKind::default().strategy(),
         }
     }
 }
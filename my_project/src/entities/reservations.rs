@@ -0,0 +1,27 @@
+use chrono::{DateTime, Utc};
+use sql_derives::SqlTable;
+
+pub const HELD: &str = "held";
+pub const CONFIRMED: &str = "confirmed";
+pub const EXPIRED: &str = "expired";
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, SqlTable, sqlx::FromRow)]
+pub struct Reservation {
+    pub id: String,
+    pub order_id: String,
+    pub sku: String,
+    #[sqlx(try_from = "i32")]
+    pub qty: u32,
+    pub status: String,
+    /// 保留建立的那筆 allocation，到期時只釋放它
+    pub allocation_id: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Reservation {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at <= now
+    }
+}
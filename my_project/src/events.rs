@@ -1,24 +1,107 @@
 use chrono::{DateTime, Utc};
 
+#[derive(Clone)]
 pub enum Event {
     BatchCreate(BatchCreate),
     AllocateRequired(AllocateRequired),
     OutOfStock(OutOfStock),
+    Reserve(Reserve),
+    ConfirmReservation(ConfirmReservation),
+    ReservationExpired(ReservationExpired),
+    BatchArrived(BatchArrived),
 }
 
+/// message bus 的信封：事件與觸發它的 request id，同一請求引發的 log 都帶相同的 id
+#[derive(Clone)]
+pub struct Envelope {
+    pub event: Event,
+    pub request_id: String,
+}
+
+impl Envelope {
+    pub fn new(event: Event, request_id: impl Into<String>) -> Self {
+        Envelope {
+            event,
+            request_id: request_id.into(),
+        }
+    }
+}
+
+tokio::task_local! {
+    /// 處理中 request 的 id，由 `sitemaps::scope_request_id` 設定
+    pub static REQUEST_ID: String;
+}
+
+/// request 中發出的事件沿用該 request 的 id；背景工作沒有 request，各自產生一個 id
+impl From<Event> for Envelope {
+    fn from(event: Event) -> Self {
+        let request_id = REQUEST_ID
+            .try_with(Clone::clone)
+            .unwrap_or_else(|_| xid::new().to_string());
+        Envelope::new(event, request_id)
+    }
+}
+
+impl Event {
+    /// 事件名稱，作為 metrics 的 label
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::BatchCreate(_) => "batch_create",
+            Event::AllocateRequired(_) => "allocate_required",
+            Event::OutOfStock(_) => "out_of_stock",
+            Event::Reserve(_) => "reserve",
+            Event::ConfirmReservation(_) => "confirm_reservation",
+            Event::ReservationExpired(_) => "reservation_expired",
+            Event::BatchArrived(_) => "batch_arrived",
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct BatchCreate {
     pub references: String,
     pub sku: String,
     pub qty: u32,
     pub eta: Option<DateTime<Utc>>,
+    pub warehouse: Option<String>,
 }
 
+#[derive(Clone)]
 pub struct AllocateRequired {
     pub order_id: String,
     pub sku: String,
     pub qty: u32,
 }
 
+#[derive(Clone)]
 pub struct OutOfStock {
     pub sku: String,
 }
+
+#[derive(Clone)]
+pub struct Reserve {
+    pub order_id: String,
+    pub sku: String,
+    pub qty: u32,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Clone)]
+pub struct ConfirmReservation {
+    pub order_id: String,
+    pub sku: String,
+}
+
+#[derive(Clone)]
+pub struct ReservationExpired {
+    pub order_id: String,
+    pub sku: String,
+    pub qty: u32,
+}
+
+#[derive(Clone)]
+pub struct BatchArrived {
+    pub reference: String,
+    pub sku: String,
+    pub qty: u32,
+}
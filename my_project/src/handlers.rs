@@ -1,26 +1,28 @@
 use std::str::FromStr;
 
+use crate::repositories::DbTransaction;
 use lettre::message::Mailbox;
 use lettre::transport::smtp::authentication::Credentials;
 use lettre::{SmtpTransport, Transport};
-use sqlx::SqliteConnection;
 
+use crate::configures::AppConfig;
 use crate::entities::batches::Batch;
 use crate::entities::products::Product;
-use crate::repositories::{create, read, read_one};
-use crate::{chapter1, events};
+use crate::repositories::{create, quote, read_one};
+use crate::{chapter1, events, services};
 
+#[tracing::instrument(name = "handler.add_batch", skip_all, fields(sku = %event.sku, reference = %event.references))]
 pub async fn add_batch(
     event: events::BatchCreate,
-    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    tx: &mut DbTransaction,
 ) -> Result<(), sqlx::Error> {
-    let db = &mut **tx;
+    let db = &mut *tx;
 
-    let where_clause_string = format!("sku = '{}'", event.sku);
+    let where_clause_string = format!("sku = {}", quote(&event.sku));
     let where_clause = Some(where_clause_string.as_str());
 
     let product_ent =
-        read_one::<&mut SqliteConnection, Product>(db, &Product::select_sql(where_clause)).await?;
+        read_one::<&mut DbTransaction, Product>(db, &Product::select_sql(where_clause)).await?;
 
     if let Some(ent) = product_ent {
         let new_batch = chapter1::Batch::new(&event.references, &event.sku, event.qty, event.eta);
@@ -32,11 +34,13 @@ pub async fn add_batch(
             sku: event.sku.to_string(),
             qty: event.qty,
             eta: event.eta,
+            warehouse: event.warehouse.clone(),
+            arrived_at: None,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         };
 
-        create::<&mut SqliteConnection>(db, &batch_ent.insert_sql()).await?;
+        create::<&mut DbTransaction>(db, &batch_ent.insert_sql()).await?;
     } else {
         let new_batch = chapter1::Batch::new(&event.references, &event.sku, event.qty, event.eta);
         let _product = chapter1::Product::new(&event.sku, vec![new_batch]);
@@ -49,7 +53,7 @@ pub async fn add_batch(
             updated_at: chrono::Utc::now(),
         };
 
-        create::<&mut SqliteConnection>(db, &ent.insert_sql()).await?;
+        create::<&mut DbTransaction>(db, &ent.insert_sql()).await?;
 
         let batch_ent = Batch {
             id: xid::new().to_string(),
@@ -57,58 +61,52 @@ pub async fn add_batch(
             sku: event.sku.to_string(),
             qty: event.qty,
             eta: event.eta,
+            warehouse: event.warehouse.clone(),
+            arrived_at: None,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         };
 
-        create::<&mut SqliteConnection>(db, &batch_ent.insert_sql()).await?;
+        create::<&mut DbTransaction>(db, &batch_ent.insert_sql()).await?;
     }
 
     Ok(())
 }
 
+#[tracing::instrument(name = "handler.allocate", skip_all, fields(sku = %event.sku, order_id = %event.order_id))]
 pub async fn allocate(
     event: events::AllocateRequired,
-    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
-) -> Result<Option<(String, i32)>, String> {
-    let db = &mut **tx;
-
-    let order = chapter1::OrderLine {
-        order_id: event.order_id.to_string(),
-        sku: event.sku.to_string(),
-        qty: event.qty,
-    };
-
-    let where_clause_string = format!("sku = '{}'", event.sku);
-    let where_clause = Some(where_clause_string.as_str());
-
-    let product_ent =
-        read_one::<&mut SqliteConnection, Product>(db, &Product::select_sql(where_clause))
-            .await
-            .unwrap();
-    if let Some(ent) = product_ent {
-        let batche_ents =
-            read::<&mut SqliteConnection, Batch>(db, &Batch::select_sql(where_clause))
-                .await
-                .unwrap();
-
-        let batches = batche_ents
-            .into_iter()
-            .map(|b| b.build())
-            .collect::<Vec<chapter1::Batch>>();
-
-        let res = ent.build(batches).allocate(&order);
-        return res;
-    } else {
-        return Err(format!("Invalid sku {}", event.sku));
+    config: &AppConfig,
+    tx: &mut DbTransaction,
+) -> Result<String, services::ServiceError> {
+    let allocated =
+        services::allocate(&event.order_id, &event.sku, event.qty, None, config, tx).await?;
+
+    match allocated {
+        chapter1::Allocated::New((batch_ref, version_number)) => {
+            services::save_version(&event.sku, version_number, tx).await?;
+            services::save_allocation(
+                &event.order_id,
+                &event.sku,
+                event.qty,
+                &[(batch_ref.clone(), event.qty)],
+                tx,
+            )
+            .await?;
+            Ok(batch_ref)
+        }
+        // 重試：明細已寫入，不再更新版本號
+        chapter1::Allocated::Existing(parts) => Ok(parts[0].0.clone()),
     }
 }
 
+#[tracing::instrument(name = "handler.send_out_of_stock_notification", skip_all, fields(sku = %event.sku))]
 pub async fn send_out_of_stock_notification(
     event: events::OutOfStock,
-    _tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
-) -> Result<(), Box<dyn std::error::Error>> {
+    _tx: &mut DbTransaction,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     // Placeholder for sending out of stock notification
+    tracing::info!("Sending out of stock notification for sku {}", event.sku);
 
     let creds = Credentials::new(
         "".to_string(),
@@ -135,3 +133,69 @@ pub async fn send_out_of_stock_notification(
     println!("Out of stock notification sent for SKU: {}", event.sku);
     Ok(())
 }
+
+#[tracing::instrument(name = "handler.reserve", skip_all, fields(sku = %event.sku, order_id = %event.order_id))]
+pub async fn reserve(
+    event: events::Reserve,
+    config: &AppConfig,
+    tx: &mut DbTransaction,
+) -> Result<String, services::ServiceError> {
+    services::reserve(
+        &event.order_id,
+        &event.sku,
+        event.qty,
+        event.expires_at,
+        config,
+        tx,
+    )
+    .await
+}
+
+#[tracing::instrument(name = "handler.confirm_reservation", skip_all, fields(sku = %event.sku, order_id = %event.order_id))]
+pub async fn confirm_reservation(
+    event: events::ConfirmReservation,
+    tx: &mut DbTransaction,
+) -> Result<(), String> {
+    services::confirm_reservation(&event.order_id, &event.sku, tx).await
+}
+
+#[tracing::instrument(name = "handler.reservation_expired", skip_all, fields(sku = %event.sku, order_id = %event.order_id))]
+pub async fn reservation_expired(
+    event: events::ReservationExpired,
+    _tx: &mut DbTransaction,
+) -> Result<(), String> {
+    tracing::info!(
+        "Reservation expired for order {} sku {} qty {}",
+        event.order_id,
+        event.sku,
+        event.qty
+    );
+    Ok(())
+}
+
+#[tracing::instrument(name = "handler.batch_arrived", skip_all, fields(sku = %event.sku, reference = %event.reference))]
+pub async fn batch_arrived(
+    event: events::BatchArrived,
+    config: &AppConfig,
+    tx: &mut DbTransaction,
+) -> Result<(), services::ServiceError> {
+    tracing::info!(
+        "Batch {} arrived for sku {} qty {}",
+        event.reference,
+        event.sku,
+        event.qty
+    );
+
+    if config.arrival.reallocate() {
+        let moved = services::reallocate_to_arrived(&event.reference, tx).await?;
+        for (order_id, from_ref) in moved {
+            tracing::info!(
+                "Reallocated order {} from {} to {}",
+                order_id,
+                from_ref,
+                event.reference
+            );
+        }
+    }
+    Ok(())
+}
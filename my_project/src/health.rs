@@ -0,0 +1,105 @@
+use std::sync::{Arc, RwLock};
+
+use axum::{
+    Json, Router, debug_handler, extract::State, http::StatusCode, response::IntoResponse,
+    routing::get,
+};
+use tokio::task::{AbortHandle, JoinHandle};
+
+use crate::repositories::DbPool;
+use crate::sitemaps::app_state::AppState;
+
+/// 背景工作清單，`/readyz` 以此判斷 sweeper 等工作是否還在執行
+#[derive(Debug, Clone, Default)]
+pub struct Workers(Arc<RwLock<Vec<(&'static str, AbortHandle)>>>);
+
+impl Workers {
+    pub fn register<T>(&self, name: &'static str, handle: &JoinHandle<T>) {
+        self.0.write().unwrap().push((name, handle.abort_handle()));
+    }
+
+    /// 已結束的背景工作名稱
+    pub fn stopped(&self) -> Vec<&'static str> {
+        self.0
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, handle)| handle.is_finished())
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
+
+/// 不經過 request tracing，避免探測請求洗版
+pub fn health_routes() -> Router<AppState> {
+    Router::new()
+        .route("/healthz", get(healthz_handler))
+        .route("/readyz", get(readyz_handler))
+        .route("/version", get(version_handler))
+}
+
+#[debug_handler]
+pub async fn healthz_handler() -> impl IntoResponse {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+#[debug_handler]
+pub async fn readyz_handler(State(app_state): State<AppState>) -> impl IntoResponse {
+    let database = app_state.db.ping().await.map_err(|e| e.to_string());
+    let migrations = match &database {
+        Ok(()) => migrations(&app_state.db).await,
+        Err(_) => Err("database unreachable".to_string()),
+    };
+    let stopped = app_state.workers.stopped();
+    let workers = if stopped.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("stopped: {}", stopped.join(", ")))
+    };
+
+    let ready = database.is_ok() && migrations.is_ok() && workers.is_ok();
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        Json(serde_json::json!({
+            "status": if ready { "ready" } else { "not_ready" },
+            "checks": {
+                "database": outcome(database),
+                "migrations": outcome(migrations),
+                "workers": outcome(workers),
+            },
+        })),
+    )
+}
+
+#[debug_handler]
+pub async fn version_handler(State(app_state): State<AppState>) -> impl IntoResponse {
+    Json(serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "git_hash": env!("GIT_HASH"),
+        "migration": app_state.db.applied_migration().await.ok().flatten(),
+    }))
+}
+
+async fn migrations(db: &DbPool) -> Result<(), String> {
+    let applied = db.applied_migration().await.map_err(|e| e.to_string())?;
+    let expected = DbPool::expected_migration();
+    if applied < expected {
+        let version = |v: Option<i64>| v.map_or("none".to_string(), |v| v.to_string());
+        return Err(format!(
+            "pending migrations: applied {}, expected {}",
+            version(applied),
+            version(expected)
+        ));
+    }
+    Ok(())
+}
+
+fn outcome(result: Result<(), String>) -> String {
+    result.err().unwrap_or_else(|| "ok".to_string())
+}
@@ -1,4 +1,8 @@
+pub mod admin;
 pub mod api_base;
+pub mod api_keys;
+pub mod arrivals;
+pub mod auth;
 pub mod chapter1;
 pub mod chapter2;
 pub mod chapter3;
@@ -6,30 +10,128 @@ pub mod configures;
 pub mod entities;
 pub mod events;
 pub mod handlers;
+pub mod health;
 pub mod messagebus;
+pub mod metrics;
+pub mod reloader;
 pub mod repositories;
+pub mod reservations;
 pub mod services;
+pub mod shutdown;
 pub mod sitemaps;
+pub mod views;
 
-pub async fn run_app() {
-    let _logs = configures::get_config().logger.load();
-    let db = configures::get_config().database.get_connection().await;
+/// 啟動或執行期間的錯誤，`exit_code` 讓部署工具分辨失敗原因
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error(transparent)]
+    Config(#[from] configures::ConfigError),
+    #[error("failed to connect to database: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("failed to run database migrations: {0}")]
+    Migrate(#[from] sqlx::migrate::MigrateError),
+    #[error("failed to bind {address}: {source}")]
+    Bind {
+        address: String,
+        source: std::io::Error,
+    },
+    #[error("server error: {0}")]
+    Serve(std::io::Error),
+}
+
+impl AppError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppError::Config(_) => 2,
+            AppError::Database(_) => 3,
+            AppError::Migrate(_) => 4,
+            AppError::Bind { .. } => 5,
+            AppError::Serve(_) => 6,
+        }
+    }
+}
+
+pub async fn run_app(config: configures::AppConfig) -> Result<(), AppError> {
+    let profile = config.server.profile();
+    let (logs, log_level) = config.logger.load(profile);
+
+    let result = serve(config, log_level).await;
+    if let Err(err) = &result {
+        tracing::error!("{}", err);
+    }
+
+    // 最後才釋放 WorkerGuard，把緩衝中的 log 寫進檔案
+    drop(logs);
+    result
+}
+
+async fn serve(
+    config: configures::AppConfig,
+    log_level: configures::LogLevel,
+) -> Result<(), AppError> {
+    let config = std::sync::Arc::new(config);
+
+    let profile = config.server.profile();
+    let runtime = configures::RuntimeConfig::new(&config, log_level);
+    let db = config.database.get_connection().await?;
     // Run database migrations
-    sqlx::migrate!("./migrations").run(&db).await.unwrap();
+    if config.database.auto_migrate_for(profile) {
+        db.migrate().await?;
+    } else {
+        tracing::info!(
+            "Skipping database migrations in {} mode",
+            config.server.app_env()
+        );
+    }
 
-    let listenert = tokio::net::TcpListener::bind(configures::get_config().server.address())
+    let listenert = tokio::net::TcpListener::bind(config.server.address())
         .await
-        .expect("Failed to bind address");
+        .map_err(|source| AppError::Bind {
+            address: config.server.address(),
+            source,
+        })?;
 
     tracing::info!(
         "Starting server at {} in {} mode",
-        configures::get_config().server.address(),
-        configures::get_config().server.app_env()
+        config.server.address(),
+        config.server.app_env()
     );
 
+    let shutdown = shutdown::Shutdown::new();
+    let workers = health::Workers::default();
+
+    tracing::info!("Starting reservation sweeper...");
+    let sweeper = reservations::spawn_sweeper(db.clone(), config.clone(), shutdown.clone());
+    workers.register("reservation_sweeper", &sweeper);
+
+    tracing::info!("Starting batch arrival checker...");
+    let checker = arrivals::spawn_checker(db.clone(), config.clone(), shutdown.clone());
+    workers.register("arrival_checker", &checker);
+
+    tracing::info!("Starting configuration watcher...");
+    let watcher = reloader::spawn_watcher(
+        configures::CONFIG_FILE.into(),
+        runtime.clone(),
+        config.server.reload_interval(),
+        shutdown.clone(),
+    );
+    workers.register("config_watcher", &watcher);
+
     tracing::info!("Starting sitemap service...");
 
-    axum::serve(listenert, sitemaps::sitemap(db).await)
-        .await
-        .unwrap();
+    // 收到訊號後不再接受新連線，等進行中的請求跑完才回傳
+    let served = axum::serve(
+        listenert,
+        sitemaps::sitemap(db.clone(), config, runtime, workers).await,
+    )
+    .with_graceful_shutdown(shutdown::signal())
+    .await;
+
+    tracing::info!("Stopping background workers...");
+    shutdown.trigger();
+    let _ = tokio::join!(sweeper, checker, watcher);
+    db.close().await;
+    tracing::info!("Shutdown complete");
+
+    served.map_err(AppError::Serve)
 }
@@ -1,8 +1,43 @@
+use architecture::configures::AppConfig;
+
 #[tokio::main]
 async fn main() {
+    // `--hash-password`：從 stdin 讀取密碼，印出可填入 `auth.users[].password_hash` 的字串
+    if std::env::args().any(|arg| arg == "--hash-password") {
+        let mut password = String::new();
+        std::io::stdin().read_line(&mut password).unwrap();
+        println!(
+            "{}",
+            architecture::auth::hash_password(password.trim_end_matches(['\r', '\n']))
+        );
+        return;
+    }
+
+    let config = match AppConfig::load() {
+        Ok(config) => config,
+        Err(e) => {
+            let err = architecture::AppError::from(e);
+            eprintln!("{}", err);
+            std::process::exit(err.exit_code());
+        }
+    };
+
+    // `--check-config`：只印出合併後的設定（遮蔽密碼）並結束
+    if std::env::args().any(|arg| arg == "--check-config") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&config.redacted()).unwrap()
+        );
+        return;
+    }
+
     println!(
         "Current dir: {}",
         std::env::current_dir().unwrap().display()
     );
-    architecture::run_app().await;
+
+    if let Err(err) = architecture::run_app(config).await {
+        eprintln!("{}", err);
+        std::process::exit(err.exit_code());
+    }
 }
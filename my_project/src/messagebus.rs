@@ -1,53 +1,106 @@
-use crate::{configures, events};
+use crate::repositories::{DbPool, DbTransaction};
 
-pub async fn headle(event: events::Event) -> Result<String, String> {
-    let db = configures::get_config().database.get_connection().await;
+use std::time::Instant;
 
+use tracing::Instrument;
+
+use crate::{configures::AppConfig, events, metrics, services::ServiceError};
+
+/// 處理事件；信封上的 request id 記在 span 上，handler 與通知的 log 都能對回原本的請求
+pub async fn headle(
+    envelope: impl Into<events::Envelope>,
+    db: &DbPool,
+    config: &AppConfig,
+) -> Result<String, String> {
+    let envelope = envelope.into();
+    let span = tracing::info_span!("messagebus", request_id = %envelope.request_id);
+
+    process(envelope.event, db, config).instrument(span).await
+}
+
+async fn process(event: events::Event, db: &DbPool, config: &AppConfig) -> Result<String, String> {
+    let bus_config = &config.messagebus;
+
+    let metrics = metrics::metrics();
     let mut queue = vec![event];
+    metrics.queue_depth().add(queue.len() as i64);
+    let mut result = Ok("Event handled successfully".to_string());
 
-    while queue.len() > 0 {
-        let mut tx = db.begin().await.map_err(|e| e.to_string())?;
+    while !queue.is_empty() {
         let ev = queue.remove(0);
-        match ev {
-            events::Event::BatchCreate(e) => match crate::handlers::add_batch(e, &mut tx).await {
-                Ok(_) => {
-                    tx.commit().await.map_err(|e| e.to_string())?;
-                    return Ok("Batch created successfully".to_string());
-                }
-                Err(err) => {
-                    tx.rollback().await.map_err(|e| e.to_string())?;
-                    return Err(err.to_string());
-                }
-            },
-            events::Event::AllocateRequired(e) => match crate::handlers::allocate(e, &mut tx).await
-            {
-                Ok(Some((message, version))) => {
+        metrics.queue_depth().dec();
+        let started = Instant::now();
+        tracing::debug!("Handling event {}", ev.name());
+
+        // 版本衝突時以新的交易重試整個命令，超過上限才回傳錯誤
+        let mut attempt = 0;
+        result = loop {
+            let mut tx = db.begin().await.map_err(|e| e.to_string())?;
+            match dispatch(ev.clone(), config, &mut tx).await {
+                Ok(message) => {
                     tx.commit().await.map_err(|e| e.to_string())?;
-                    return Ok(message);
-                }
-                Ok(None) => {
-                    tx.rollback().await.map_err(|e| e.to_string())?;
-                    return Err("Allocation failed: None returned".to_string());
+                    if matches!(ev, events::Event::AllocateRequired(_)) {
+                        metrics.allocation("allocated");
+                    }
+                    break Ok(message);
                 }
                 Err(err) => {
                     tx.rollback().await.map_err(|e| e.to_string())?;
-                    return Err(err.to_string());
-                }
-            },
-            events::Event::OutOfStock(e) => {
-                match crate::handlers::send_out_of_stock_notification(e, &mut tx).await {
-                    Ok(_) => {
-                        tx.commit().await.map_err(|e| e.to_string())?;
-                        return Ok("Out of stock notification sent".to_string());
+                    if err.is_conflict() && attempt < bus_config.max_retries() {
+                        tracing::warn!("Retrying after concurrency conflict: {}", err);
+                        tokio::time::sleep(bus_config.backoff(attempt)).await;
+                        attempt += 1;
+                        continue;
                     }
-                    Err(err) => {
-                        tx.rollback().await.map_err(|e| e.to_string())?;
-                        return Err(err.to_string());
+                    if matches!(ev, events::Event::AllocateRequired(_)) {
+                        metrics.allocation_error(&err);
                     }
+                    break Err(err.to_string());
                 }
             }
+        };
+        metrics.handler_duration(ev.name(), started);
+
+        if result.is_err() {
+            break;
         }
     }
+    // 失敗時未處理的事件不再計入佇列
+    metrics.queue_depth().sub(queue.len() as i64);
+
+    result
+}
 
-    Ok("Event handled successfully".to_string())
+#[tracing::instrument(skip_all, fields(event = event.name()))]
+async fn dispatch(
+    event: events::Event,
+    config: &AppConfig,
+    tx: &mut DbTransaction,
+) -> Result<String, ServiceError> {
+    match event {
+        events::Event::BatchCreate(e) => {
+            crate::handlers::add_batch(e, tx).await?;
+            Ok("Batch created successfully".to_string())
+        }
+        events::Event::AllocateRequired(e) => Ok(crate::handlers::allocate(e, config, tx).await?),
+        events::Event::OutOfStock(e) => {
+            crate::handlers::send_out_of_stock_notification(e, tx)
+                .await
+                .map_err(|err| ServiceError::Invalid(err.to_string()))?;
+            Ok("Out of stock notification sent".to_string())
+        }
+        events::Event::Reserve(e) => Ok(crate::handlers::reserve(e, config, tx).await?),
+        events::Event::ConfirmReservation(e) => {
+            crate::handlers::confirm_reservation(e, tx).await?;
+            Ok("Reservation confirmed".to_string())
+        }
+        events::Event::ReservationExpired(e) => {
+            crate::handlers::reservation_expired(e, tx).await?;
+            Ok("Reservation expiry handled".to_string())
+        }
+        events::Event::BatchArrived(e) => {
+            crate::handlers::batch_arrived(e, config, tx).await?;
+            Ok("Batch arrival handled".to_string())
+        }
+    }
 }
@@ -0,0 +1,185 @@
+use std::sync::LazyLock;
+use std::time::Instant;
+
+use axum::{
+    Router,
+    body::Body,
+    debug_handler,
+    extract::{MatchedPath, State},
+    http::{Request, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+
+use crate::repositories::DbPool;
+use crate::services::ServiceError;
+use crate::sitemaps::app_state::AppState;
+
+/// 全程序共用的指標，message bus 與背景工作不經過 AppState 也能記錄
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    allocations: IntCounterVec,
+    queue_depth: IntGauge,
+    handler_duration: HistogramVec,
+    db_connections: IntGaugeVec,
+    db_max_connections: IntGauge,
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route and status"),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "HTTP request latency by route",
+            ),
+            &["method", "route"],
+        )
+        .unwrap();
+        let allocations = IntCounterVec::new(
+            Opts::new("allocations_total", "Allocation attempts by outcome"),
+            &["outcome"],
+        )
+        .unwrap();
+        let queue_depth = IntGauge::new(
+            "messagebus_queue_depth",
+            "Events waiting in the message bus",
+        )
+        .unwrap();
+        let handler_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "messagebus_handler_duration_seconds",
+                "Message bus handler latency by event, including retries",
+            ),
+            &["event"],
+        )
+        .unwrap();
+        let db_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Database pool connections by state"),
+            &["state"],
+        )
+        .unwrap();
+        let db_max_connections =
+            IntGauge::new("db_pool_max_connections", "Configured database pool size").unwrap();
+
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry.register(Box::new(http_duration.clone())).unwrap();
+        registry.register(Box::new(allocations.clone())).unwrap();
+        registry.register(Box::new(queue_depth.clone())).unwrap();
+        registry
+            .register(Box::new(handler_duration.clone()))
+            .unwrap();
+        registry.register(Box::new(db_connections.clone())).unwrap();
+        registry
+            .register(Box::new(db_max_connections.clone()))
+            .unwrap();
+
+        Self {
+            registry,
+            http_requests,
+            http_duration,
+            allocations,
+            queue_depth,
+            handler_duration,
+            db_connections,
+            db_max_connections,
+        }
+    }
+
+    /// 配貨結果：allocated、out_of_stock、conflict、failed
+    pub fn allocation(&self, outcome: &str) {
+        self.allocations.with_label_values(&[outcome]).inc();
+    }
+
+    pub fn allocation_error(&self, err: &ServiceError) {
+        self.allocation(match err {
+            ServiceError::ConcurrencyConflict(_) => "conflict",
+            ServiceError::OutOfStock(_) => "out_of_stock",
+            ServiceError::Invalid(_) | ServiceError::Database(_) => "failed",
+        });
+    }
+
+    pub fn queue_depth(&self) -> &IntGauge {
+        &self.queue_depth
+    }
+
+    pub fn handler_duration(&self, event: &str, started: Instant) {
+        self.handler_duration
+            .with_label_values(&[event])
+            .observe(started.elapsed().as_secs_f64());
+    }
+
+    /// 連線池用量在抓取時才讀取
+    pub fn render(&self, db: &DbPool) -> String {
+        let (size, idle) = db.usage();
+        self.db_connections
+            .with_label_values(&["idle"])
+            .set(idle as i64);
+        self.db_connections
+            .with_label_values(&["active"])
+            .set(size as i64 - idle as i64);
+        self.db_max_connections.set(db.max_connections() as i64);
+
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+}
+
+pub fn metrics_routes() -> Router<AppState> {
+    Router::new().route("/metrics", get(metrics_handler))
+}
+
+#[debug_handler]
+pub async fn metrics_handler(State(app_state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)],
+        metrics().render(&app_state.db),
+    )
+}
+
+/// 以 route 樣板（例如 `/products/{sku}`）作為 label，避免路徑參數造成大量時間序列
+pub async fn track_http(request: Request<Body>, next: Next) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let response = next.run(request).await;
+
+    let metrics = metrics();
+    metrics
+        .http_requests
+        .with_label_values(&[&method, &route, response.status().as_str()])
+        .inc();
+    metrics
+        .http_duration
+        .with_label_values(&[&method, &route])
+        .observe(started.elapsed().as_secs_f64());
+
+    response
+}
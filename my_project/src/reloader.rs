@@ -0,0 +1,57 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use tokio::task::JoinHandle;
+
+use crate::configures::{AppConfig, RuntimeConfig};
+use crate::shutdown::Shutdown;
+
+/// 重新讀取設定檔並套用可熱更新的部分，回傳有變更的 key
+pub fn reload(path: &Path, runtime: &RuntimeConfig) -> Result<Vec<&'static str>, String> {
+    let config = AppConfig::load_from(path).map_err(|e| e.to_string())?;
+    runtime.apply(&config)
+}
+
+fn modified(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|file| std::fs::metadata(file).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+/// 背景監看設定檔：修改時間變更就重新載入，載入失敗時保留目前的設定
+pub fn spawn_watcher(
+    path: PathBuf,
+    runtime: RuntimeConfig,
+    interval: Duration,
+    shutdown: Shutdown,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let files = AppConfig::files(&path, &runtime.server().app_env());
+        let mut last = modified(&files);
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = shutdown.wait() => break,
+            }
+
+            let current = modified(&files);
+            if current == last {
+                continue;
+            }
+            last = current;
+
+            match reload(&path, &runtime) {
+                Ok(changed) if changed.is_empty() => {
+                    tracing::info!("Configuration reloaded, no runtime settings changed")
+                }
+                Ok(changed) => tracing::info!("Configuration reloaded: {}", changed.join(", ")),
+                Err(err) => tracing::warn!("Keeping current configuration: {}", err),
+            }
+        }
+
+        tracing::info!("Configuration watcher stopped");
+    })
+}
@@ -1,144 +1,261 @@
+mod pool;
+
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use serde_json::Map;
 use serde_json::Value as JsonValue;
 use sqlx::Column;
 use sqlx::Row;
 use sqlx::Value; // ★ 必須
 use sqlx::ValueRef;
-use sqlx::sqlite::SqliteQueryResult;
+use sqlx::postgres::PgValue;
 use sqlx::sqlite::SqliteValue;
 
-fn decode_any_value(raw: SqliteValue) -> JsonValue {
-    // 整數
-    if let Ok(opt) = raw.try_decode::<Option<i64>>() {
-        return opt.map(JsonValue::from).unwrap_or(JsonValue::Null);
-    }
-    if let Ok(opt) = raw.try_decode::<Option<i32>>() {
-        return opt.map(JsonValue::from).unwrap_or(JsonValue::Null);
-    }
-    if let Ok(opt) = raw.try_decode::<Option<i16>>() {
-        return opt.map(JsonValue::from).unwrap_or(JsonValue::Null);
-    }
+pub use crate::repositories::pool::{DbExecutor, DbPool, DbTransaction, Record};
 
-    // 浮點
-    if let Ok(opt) = raw.try_decode::<Option<f64>>() {
-        return opt.map(JsonValue::from).unwrap_or(JsonValue::Null);
-    }
-    if let Ok(opt) = raw.try_decode::<Option<f32>>() {
-        return opt
-            .map(|v| JsonValue::from(v as f64))
-            .unwrap_or(JsonValue::Null);
-    }
+/// span 只記錄 SQL 的操作與資料表，條件中的字面值（訂單編號、金鑰雜湊）不送往 collector
+fn operation(sql: &str) -> &str {
+    sql.split_whitespace().next().unwrap_or_default()
+}
 
-    // bool
-    if let Ok(opt) = raw.try_decode::<Option<bool>>() {
-        return opt.map(JsonValue::from).unwrap_or(JsonValue::Null);
+/// 第一個 `FROM`、`INTO` 或 `UPDATE` 之後的資料表
+fn collection(sql: &str) -> &str {
+    let mut words = sql.split_whitespace();
+    while let Some(word) = words.next() {
+        if ["FROM", "INTO", "UPDATE"]
+            .iter()
+            .any(|keyword| word.eq_ignore_ascii_case(keyword))
+        {
+            return words
+                .next()
+                .map(|table| table.trim_matches(|c: char| !c.is_alphanumeric() && c != '_'))
+                .unwrap_or_default();
+        }
     }
+    ""
+}
 
-    // 字串
-    if let Ok(opt) = raw.try_decode::<Option<String>>() {
-        return opt.map(JsonValue::from).unwrap_or(JsonValue::Null);
-    }
+/// 依連線池或交易的資料庫種類展開同一段查詢
+macro_rules! dispatch {
+    ($executor:expr, |$conn:ident| $body:expr) => {
+        match $executor.into() {
+            DbExecutor::Pool(DbPool::Sqlite($conn)) => $body,
+            DbExecutor::Pool(DbPool::Postgres($conn)) => $body,
+            DbExecutor::Transaction(DbTransaction::Sqlite(tx)) => {
+                let $conn = &mut **tx;
+                $body
+            }
+            DbExecutor::Transaction(DbTransaction::Postgres(tx)) => {
+                let $conn = &mut **tx;
+                $body
+            }
+        }
+    };
+}
 
-    // bytes → base64（新版 API）
-    if let Ok(opt) = raw.try_decode::<Option<Vec<u8>>>() {
-        return match opt {
-            None => JsonValue::Null,
-            Some(bytes) => JsonValue::String(STANDARD.encode(bytes)),
-        };
-    }
+/// 將字串轉成 SQL 字串常值（單引號跳脫），用於組合 WHERE 條件
+pub fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
 
-    // fallback: 無法 decode，回傳類型名稱
-    JsonValue::String(format!("<unsupported: {}>", raw.type_info()))
+/// 動態欄位轉成 JSON 的方式依資料庫而不同
+trait JsonDecode: sqlx::Database {
+    fn to_json(raw: Self::Value) -> JsonValue;
 }
 
-/// 主函式：執行任意 SQL，將每一列轉成 serde_json::Value（動態欄位）
-pub async fn read_to_json<'a, E>(pool: E, sql: &str) -> Result<Vec<JsonValue>, sqlx::Error>
-where
-    E: sqlx::Executor<'a, Database = sqlx::Sqlite> + Copy,
-{
-    let rows = sqlx::query(sql).fetch_all(pool).await?;
+impl JsonDecode for sqlx::Sqlite {
+    fn to_json(raw: SqliteValue) -> JsonValue {
+        // 整數
+        if let Ok(opt) = raw.try_decode::<Option<i64>>() {
+            return opt.map(JsonValue::from).unwrap_or(JsonValue::Null);
+        }
+        if let Ok(opt) = raw.try_decode::<Option<i32>>() {
+            return opt.map(JsonValue::from).unwrap_or(JsonValue::Null);
+        }
+        if let Ok(opt) = raw.try_decode::<Option<i16>>() {
+            return opt.map(JsonValue::from).unwrap_or(JsonValue::Null);
+        }
+
+        // 浮點
+        if let Ok(opt) = raw.try_decode::<Option<f64>>() {
+            return opt.map(JsonValue::from).unwrap_or(JsonValue::Null);
+        }
+        if let Ok(opt) = raw.try_decode::<Option<f32>>() {
+            return opt
+                .map(|v| JsonValue::from(v as f64))
+                .unwrap_or(JsonValue::Null);
+        }
 
-    let mut out = Vec::with_capacity(rows.len());
+        // bool
+        if let Ok(opt) = raw.try_decode::<Option<bool>>() {
+            return opt.map(JsonValue::from).unwrap_or(JsonValue::Null);
+        }
 
-    for row in rows {
-        let mut obj = Map::new();
+        // 字串
+        if let Ok(opt) = raw.try_decode::<Option<String>>() {
+            return opt.map(JsonValue::from).unwrap_or(JsonValue::Null);
+        }
 
-        // 透過 index 取得 raw value（避免 name 重複 / alias 問題）
-        for (i, col) in row.columns().iter().enumerate() {
-            let name = (*col).name().to_string();
-            let raw = row.try_get_raw(i)?;
-            let val = decode_any_value(ValueRef::to_owned(&raw));
-            obj.insert(name, val.into());
+        // bytes → base64（新版 API）
+        if let Ok(opt) = raw.try_decode::<Option<Vec<u8>>>() {
+            return match opt {
+                None => JsonValue::Null,
+                Some(bytes) => JsonValue::String(STANDARD.encode(bytes)),
+            };
         }
 
-        out.push(JsonValue::Object(obj));
+        // fallback: 無法 decode，回傳類型名稱
+        JsonValue::String(format!("<unsupported: {}>", raw.type_info()))
     }
+}
 
-    Ok(out)
+impl JsonDecode for sqlx::Postgres {
+    fn to_json(raw: PgValue) -> JsonValue {
+        if let Ok(opt) = raw.try_decode::<Option<i64>>() {
+            return opt.map(JsonValue::from).unwrap_or(JsonValue::Null);
+        }
+        if let Ok(opt) = raw.try_decode::<Option<i32>>() {
+            return opt.map(JsonValue::from).unwrap_or(JsonValue::Null);
+        }
+        if let Ok(opt) = raw.try_decode::<Option<i16>>() {
+            return opt.map(JsonValue::from).unwrap_or(JsonValue::Null);
+        }
+        if let Ok(opt) = raw.try_decode::<Option<f64>>() {
+            return opt.map(JsonValue::from).unwrap_or(JsonValue::Null);
+        }
+        if let Ok(opt) = raw.try_decode::<Option<bool>>() {
+            return opt.map(JsonValue::from).unwrap_or(JsonValue::Null);
+        }
+        if let Ok(opt) = raw.try_decode::<Option<String>>() {
+            return opt.map(JsonValue::from).unwrap_or(JsonValue::Null);
+        }
+
+        // 時間欄位輸出成與 SQLite 儲存時相同的 RFC 3339 字串
+        if let Ok(opt) = raw.try_decode::<Option<DateTime<Utc>>>() {
+            return opt
+                .map(|v| JsonValue::from(v.to_rfc3339()))
+                .unwrap_or(JsonValue::Null);
+        }
+        if let Ok(opt) = raw.try_decode::<Option<NaiveDateTime>>() {
+            return opt
+                .map(|v| JsonValue::from(v.to_string()))
+                .unwrap_or(JsonValue::Null);
+        }
+        if let Ok(opt) = raw.try_decode::<Option<NaiveDate>>() {
+            return opt
+                .map(|v| JsonValue::from(v.to_string()))
+                .unwrap_or(JsonValue::Null);
+        }
+
+        if let Ok(opt) = raw.try_decode::<Option<Vec<u8>>>() {
+            return match opt {
+                None => JsonValue::Null,
+                Some(bytes) => JsonValue::String(STANDARD.encode(bytes)),
+            };
+        }
+
+        JsonValue::String(format!("<unsupported: {}>", raw.type_info()))
+    }
 }
 
-pub async fn read_one_to_json<'a, E>(pool: E, sql: &str) -> Result<Option<JsonValue>, sqlx::Error>
+fn row_to_json<R>(row: &R) -> Result<JsonValue, sqlx::Error>
 where
-    E: sqlx::Executor<'a, Database = sqlx::Sqlite> + Copy,
+    R: Row,
+    R::Database: JsonDecode,
+    usize: sqlx::ColumnIndex<R>,
 {
-    let row_opt = sqlx::query(sql).fetch_optional(pool).await?;
+    let mut obj = Map::new();
 
-    if let Some(row) = row_opt {
-        let mut obj = Map::new();
+    // 透過 index 取得 raw value（避免 name 重複 / alias 問題）
+    for (i, col) in row.columns().iter().enumerate() {
+        let name = (*col).name().to_string();
+        let raw = row.try_get_raw(i)?;
+        let val = <R::Database as JsonDecode>::to_json(ValueRef::to_owned(&raw));
+        obj.insert(name, val);
+    }
 
-        for (i, col) in row.columns().iter().enumerate() {
-            let name = (*col).name().to_string();
-            let raw = row.try_get_raw(i)?;
-            let val = decode_any_value(ValueRef::to_owned(&raw));
-            obj.insert(name, val.into());
-        }
+    Ok(JsonValue::Object(obj))
+}
 
-        Ok(Some(JsonValue::Object(obj)))
-    } else {
-        Ok(None)
-    }
+/// 主函式：執行任意 SQL，將每一列轉成 serde_json::Value（動態欄位）
+/// 每次呼叫各自一個 span，記錄操作與資料表供 trace 查看
+#[tracing::instrument(name = "repository.read_to_json", skip_all, fields(db.operation.name = operation(sql), db.collection.name = collection(sql)))]
+pub async fn read_to_json<'a, E>(executor: E, sql: &str) -> Result<Vec<JsonValue>, sqlx::Error>
+where
+    E: Into<DbExecutor<'a>>,
+{
+    dispatch!(executor, |conn| {
+        let rows = sqlx::query(sql).fetch_all(conn).await?;
+        rows.iter().map(row_to_json).collect()
+    })
+}
+
+#[tracing::instrument(name = "repository.read_one_to_json", skip_all, fields(db.operation.name = operation(sql), db.collection.name = collection(sql)))]
+pub async fn read_one_to_json<'a, E>(
+    executor: E,
+    sql: &str,
+) -> Result<Option<JsonValue>, sqlx::Error>
+where
+    E: Into<DbExecutor<'a>>,
+{
+    dispatch!(executor, |conn| {
+        let row_opt = sqlx::query(sql).fetch_optional(conn).await?;
+        row_opt.as_ref().map(row_to_json).transpose()
+    })
+}
+
+#[tracing::instrument(name = "repository.read", skip_all, fields(db.operation.name = operation(sql), db.collection.name = collection(sql)))]
+pub async fn read<'a, E, T>(executor: E, sql: &str) -> Result<Vec<T>, sqlx::Error>
+where
+    E: Into<DbExecutor<'a>>,
+    T: Record,
+{
+    dispatch!(executor, |conn| sqlx::query_as(sql).fetch_all(conn).await)
 }
 
-pub async fn read<'a, E, T>(pool: E, sql: &str) -> Result<Vec<T>, sqlx::Error>
+#[tracing::instrument(name = "repository.read_one", skip_all, fields(db.operation.name = operation(sql), db.collection.name = collection(sql)))]
+pub async fn read_one<'a, E, T>(executor: E, sql: &str) -> Result<Option<T>, sqlx::Error>
 where
-    E: sqlx::Executor<'a, Database = sqlx::Sqlite>,
-    T: for<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> + Send + Unpin,
+    E: Into<DbExecutor<'a>>,
+    T: Record,
 {
-    let rows: Vec<T> = sqlx::query_as(sql).fetch_all(pool).await?;
-    Ok(rows)
+    dispatch!(executor, |conn| sqlx::query_as(sql)
+        .fetch_optional(conn)
+        .await)
 }
 
-pub async fn read_one<'a, E, T>(pool: E, sql: &str) -> Result<Option<T>, sqlx::Error>
+/// 回傳影響的筆數
+#[tracing::instrument(name = "repository.create", skip_all, fields(db.operation.name = operation(sql), db.collection.name = collection(sql)))]
+pub async fn create<'a, E>(executor: E, sql: &str) -> Result<u64, sqlx::Error>
 where
-    E: sqlx::Executor<'a, Database = sqlx::Sqlite>,
-    T: for<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> + Send + Unpin,
+    E: Into<DbExecutor<'a>>,
 {
-    let row_opt: Option<T> = sqlx::query_as(sql).fetch_optional(pool).await?;
-    Ok(row_opt)
+    execute(executor, sql).await
 }
 
-pub async fn create<'a, E>(pool: E, sql: &str) -> Result<SqliteQueryResult, sqlx::Error>
+#[tracing::instrument(name = "repository.update", skip_all, fields(db.operation.name = operation(sql), db.collection.name = collection(sql)))]
+pub async fn update<'a, E>(executor: E, sql: &str) -> Result<u64, sqlx::Error>
 where
-    E: sqlx::Executor<'a, Database = sqlx::Sqlite>,
+    E: Into<DbExecutor<'a>>,
 {
-    let query_result = sqlx::query(sql).execute(pool).await?;
-    Ok(query_result)
+    execute(executor, sql).await
 }
 
-pub async fn update<'a, E>(pool: E, sql: &str) -> Result<SqliteQueryResult, sqlx::Error>
+#[tracing::instrument(name = "repository.delete", skip_all, fields(db.operation.name = operation(sql), db.collection.name = collection(sql)))]
+pub async fn delete<'a, E>(executor: E, sql: &str) -> Result<u64, sqlx::Error>
 where
-    E: sqlx::Executor<'a, Database = sqlx::Sqlite>,
+    E: Into<DbExecutor<'a>>,
 {
-    let query_result = sqlx::query(sql).execute(pool).await?;
-    Ok(query_result)
+    execute(executor, sql).await
 }
 
-pub async fn delete<'a, E>(pool: E, sql: &str) -> Result<SqliteQueryResult, sqlx::Error>
+async fn execute<'a, E>(executor: E, sql: &str) -> Result<u64, sqlx::Error>
 where
-    E: sqlx::Executor<'a, Database = sqlx::Sqlite>,
+    E: Into<DbExecutor<'a>>,
 {
-    let query_result = sqlx::query(sql).execute(pool).await?;
-    Ok(query_result)
+    dispatch!(executor, |conn| sqlx::query(sql)
+        .execute(conn)
+        .await
+        .map(|result| result.rows_affected()))
 }
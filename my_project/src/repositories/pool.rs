@@ -0,0 +1,134 @@
+use sqlx::migrate::Migrator;
+use sqlx::postgres::{PgPool, PgRow};
+use sqlx::sqlite::{SqlitePool, SqliteRow};
+use sqlx::{FromRow, Postgres, Sqlite, Transaction};
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// 依 `database.driver` 建立的連線池，repositories 對兩種資料庫執行同一套 SQL
+#[derive(Debug, Clone)]
+pub enum DbPool {
+    Sqlite(SqlitePool),
+    Postgres(PgPool),
+}
+
+impl DbPool {
+    pub async fn begin(&self) -> Result<DbTransaction, sqlx::Error> {
+        Ok(match self {
+            DbPool::Sqlite(pool) => DbTransaction::Sqlite(pool.begin().await?),
+            DbPool::Postgres(pool) => DbTransaction::Postgres(pool.begin().await?),
+        })
+    }
+
+    pub async fn migrate(&self) -> Result<(), sqlx::migrate::MigrateError> {
+        match self {
+            DbPool::Sqlite(pool) => MIGRATOR.run(pool).await,
+            DbPool::Postgres(pool) => MIGRATOR.run(pool).await,
+        }
+    }
+
+    /// 編進執行檔的最新 migration 版本
+    pub fn expected_migration() -> Option<i64> {
+        MIGRATOR.iter().map(|migration| migration.version).max()
+    }
+
+    /// 資料庫已套用的最新 migration 版本，尚未套用任何 migration 時為 None
+    pub async fn applied_migration(&self) -> Result<Option<i64>, sqlx::Error> {
+        let sql = "SELECT MAX(version) FROM _sqlx_migrations WHERE success";
+        match self {
+            DbPool::Sqlite(pool) => sqlx::query_scalar(sql).fetch_one(pool).await,
+            DbPool::Postgres(pool) => sqlx::query_scalar(sql).fetch_one(pool).await,
+        }
+    }
+
+    pub async fn ping(&self) -> Result<(), sqlx::Error> {
+        match self {
+            DbPool::Sqlite(pool) => sqlx::query("SELECT 1").execute(pool).await.map(|_| ()),
+            DbPool::Postgres(pool) => sqlx::query("SELECT 1").execute(pool).await.map(|_| ()),
+        }
+    }
+
+    pub async fn close(&self) {
+        match self {
+            DbPool::Sqlite(pool) => pool.close().await,
+            DbPool::Postgres(pool) => pool.close().await,
+        }
+    }
+
+    /// 目前開啟的連線數與其中閒置的數量
+    pub fn usage(&self) -> (u32, usize) {
+        match self {
+            DbPool::Sqlite(pool) => (pool.size(), pool.num_idle()),
+            DbPool::Postgres(pool) => (pool.size(), pool.num_idle()),
+        }
+    }
+
+    pub fn max_connections(&self) -> u32 {
+        match self {
+            DbPool::Sqlite(pool) => pool.options().get_max_connections(),
+            DbPool::Postgres(pool) => pool.options().get_max_connections(),
+        }
+    }
+}
+
+impl From<SqlitePool> for DbPool {
+    fn from(pool: SqlitePool) -> Self {
+        DbPool::Sqlite(pool)
+    }
+}
+
+impl From<PgPool> for DbPool {
+    fn from(pool: PgPool) -> Self {
+        DbPool::Postgres(pool)
+    }
+}
+
+pub enum DbTransaction {
+    Sqlite(Transaction<'static, Sqlite>),
+    Postgres(Transaction<'static, Postgres>),
+}
+
+impl DbTransaction {
+    pub async fn commit(self) -> Result<(), sqlx::Error> {
+        match self {
+            DbTransaction::Sqlite(tx) => tx.commit().await,
+            DbTransaction::Postgres(tx) => tx.commit().await,
+        }
+    }
+
+    pub async fn rollback(self) -> Result<(), sqlx::Error> {
+        match self {
+            DbTransaction::Sqlite(tx) => tx.rollback().await,
+            DbTransaction::Postgres(tx) => tx.rollback().await,
+        }
+    }
+}
+
+/// 執行 SQL 的對象：連線池或交易
+pub enum DbExecutor<'a> {
+    Pool(&'a DbPool),
+    Transaction(&'a mut DbTransaction),
+}
+
+impl<'a> From<&'a DbPool> for DbExecutor<'a> {
+    fn from(pool: &'a DbPool) -> Self {
+        DbExecutor::Pool(pool)
+    }
+}
+
+impl<'a> From<&'a mut DbTransaction> for DbExecutor<'a> {
+    fn from(tx: &'a mut DbTransaction) -> Self {
+        DbExecutor::Transaction(tx)
+    }
+}
+
+/// 兩種資料庫都能解碼的資料列
+pub trait Record:
+    for<'r> FromRow<'r, SqliteRow> + for<'r> FromRow<'r, PgRow> + Send + Unpin
+{
+}
+
+impl<T> Record for T where
+    T: for<'r> FromRow<'r, SqliteRow> + for<'r> FromRow<'r, PgRow> + Send + Unpin
+{
+}
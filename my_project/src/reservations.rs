@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use crate::repositories::DbPool;
+use tokio::task::JoinHandle;
+
+use crate::shutdown::Shutdown;
+use crate::{configures::AppConfig, events, messagebus, services};
+
+/// 逐筆釋放到期的保留，每筆各用一個交易，單筆失敗只記錄 log 不影響其他保留；
+/// 回傳需要發布的 ReservationExpired 事件
+pub async fn sweep(db: &DbPool) -> Result<Vec<events::ReservationExpired>, String> {
+    let held = services::expired_reservations(chrono::Utc::now(), db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut expired = Vec::new();
+    for reservation in held {
+        let mut tx = db.begin().await.map_err(|e| e.to_string())?;
+
+        match services::expire_reservation(&reservation, &mut tx).await {
+            Ok(event) => {
+                tx.commit().await.map_err(|e| e.to_string())?;
+                expired.extend(event);
+            }
+            Err(err) => {
+                tx.rollback().await.map_err(|e| e.to_string())?;
+                tracing::warn!("Failed to expire reservation {}: {}", reservation.id, err);
+            }
+        }
+    }
+
+    Ok(expired)
+}
+
+/// 背景 sweeper：定期釋放到期保留，並將 ReservationExpired 事件送進 message bus
+pub fn spawn_sweeper(db: DbPool, config: Arc<AppConfig>, shutdown: Shutdown) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(config.reservation.sweep_interval());
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = shutdown.wait() => break,
+            }
+
+            match sweep(&db).await {
+                Ok(expired) => {
+                    for event in expired {
+                        if let Err(err) = messagebus::headle(
+                            events::Event::ReservationExpired(event),
+                            &db,
+                            &config,
+                        )
+                        .await
+                        {
+                            tracing::warn!("Failed to publish ReservationExpired: {}", err);
+                        }
+                    }
+                }
+                Err(err) => tracing::error!("Reservation sweep failed: {}", err),
+            }
+        }
+
+        tracing::info!("Reservation sweeper stopped");
+    })
+}
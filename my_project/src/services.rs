@@ -1,65 +1,401 @@
 use chrono::{DateTime, Utc};
-use sqlx::{SqliteConnection, Transaction};
 
 use crate::{
     chapter1,
-    entities::{batches::Batch, products::Product},
-    repositories::{create, read, read_one},
+    configures::AppConfig,
+    entities::{
+        allocations::Allocation,
+        batches::Batch,
+        order_lines::OrderLine,
+        products::Product,
+        reservations::{self, Reservation},
+    },
+    events,
+    repositories::{DbPool, DbTransaction, create, delete, quote, read, read_one, update},
 };
 
+/// 服務層錯誤：版本衝突與一般的業務錯誤分開，讓呼叫端可以重試或回應 409
+#[derive(Debug, thiserror::Error)]
+pub enum ServiceError {
+    #[error("{0}")]
+    ConcurrencyConflict(String),
+    #[error("{0}")]
+    OutOfStock(String),
+    #[error("{0}")]
+    Invalid(String),
+    /// 併發衝突以外的資料庫錯誤
+    #[error("{0}")]
+    Database(String),
+}
+
+impl ServiceError {
+    pub fn is_conflict(&self) -> bool {
+        matches!(self, ServiceError::ConcurrencyConflict(_))
+    }
+}
+
+impl From<String> for ServiceError {
+    fn from(message: String) -> Self {
+        ServiceError::Invalid(message)
+    }
+}
+
+impl From<chapter1::AllocationError> for ServiceError {
+    // 缺貨另外分類供 metrics 統計
+    fn from(err: chapter1::AllocationError) -> Self {
+        match err {
+            chapter1::AllocationError::OutOfStock(_) => ServiceError::OutOfStock(err.to_string()),
+            chapter1::AllocationError::AlreadyAllocated { .. } => {
+                ServiceError::Invalid(err.to_string())
+            }
+        }
+    }
+}
+
+impl From<sqlx::Error> for ServiceError {
+    fn from(err: sqlx::Error) -> Self {
+        // SQLite 的寫入鎖衝突（SQLITE_BUSY 系列）與 Postgres 的序列化失敗、死結同樣視為併發衝突
+        // SQLite 的 extended code 低 8 位是主要錯誤碼，只能用在 SQLite，Postgres 的 SQLSTATE 直接比對
+        let conflict = err.as_database_error().is_some_and(|e| {
+            let code = e.code().unwrap_or_default();
+            if e.try_downcast_ref::<sqlx::sqlite::SqliteError>().is_some() {
+                code.parse::<i32>().is_ok_and(|code| code & 0xff == 5)
+            } else if e
+                .try_downcast_ref::<sqlx::postgres::PgDatabaseError>()
+                .is_some()
+            {
+                matches!(code.as_ref(), "40001" | "40P01")
+            } else {
+                false
+            }
+        });
+
+        if conflict {
+            ServiceError::ConcurrencyConflict(err.to_string())
+        } else {
+            ServiceError::Database(err.to_string())
+        }
+    }
+}
+
+impl From<ServiceError> for String {
+    fn from(err: ServiceError) -> Self {
+        err.to_string()
+    }
+}
+
 pub async fn allocate(
     order_id: &str,
     sku: &str,
     qty: u32,
-    tx: &mut Transaction<'_, sqlx::Sqlite>,
-) -> Result<Option<(String, i32)>, String> {
-    let db = &mut **tx;
-
+    region: Option<&str>,
+    config: &AppConfig,
+    tx: &mut DbTransaction,
+) -> Result<chapter1::Allocated<(String, i32)>, ServiceError> {
     let order = chapter1::OrderLine {
         order_id: order_id.to_string(),
         sku: sku.to_string(),
         qty,
     };
 
-    let where_clause_string = format!("sku = '{}'", sku);
-    let where_clause = Some(where_clause_string.as_str());
+    let product = load_product_for(tx, sku, region, config).await?;
+    if let Some(mut product) = product {
+        Ok(product.allocate(&order)?)
+    } else {
+        Err(format!("Invalid sku {}", sku).into())
+    }
+}
 
-    let product_ent =
-        read_one::<&mut SqliteConnection, Product>(db, &Product::select_sql(where_clause))
-            .await
-            .unwrap();
-    if let Some(ent) = product_ent {
-        let batche_ents =
-            read::<&mut SqliteConnection, Batch>(db, &Batch::select_sql(where_clause))
-                .await
-                .unwrap();
-
-        let batches = batche_ents
-            .into_iter()
-            .map(|b| b.build())
-            .collect::<Vec<chapter1::Batch>>();
-
-        let res = ent.build(batches).allocate(&order);
-        return res;
+/// 整筆訂單分配：所有明細在同一個交易中分配並寫入，任一明細失敗即回傳錯誤，由呼叫端 rollback
+pub async fn allocate_order(
+    order_id: &str,
+    lines: &[(String, u32)],
+    region: Option<&str>,
+    config: &AppConfig,
+    tx: &mut DbTransaction,
+) -> Result<Vec<(String, String)>, ServiceError> {
+    let mut results = Vec::with_capacity(lines.len());
+
+    for (sku, qty) in lines {
+        // 重試時明細已寫入，沿用既有的批次
+        let batch_ref = match allocate(order_id, sku, *qty, region, config, tx).await? {
+            chapter1::Allocated::New((batch_ref, version_number)) => {
+                save_version(sku, version_number, tx).await?;
+                save_allocation(order_id, sku, *qty, &[(batch_ref.clone(), *qty)], tx).await?;
+                batch_ref
+            }
+            chapter1::Allocated::Existing(parts) => parts[0].0.clone(),
+        };
+
+        results.push((sku.clone(), batch_ref));
+    }
+
+    Ok(results)
+}
+
+/// 分批分配：單一批次不足時拆分到多個批次，回傳 (批次, 數量) 列表
+pub async fn allocate_parts(
+    order_id: &str,
+    sku: &str,
+    qty: u32,
+    region: Option<&str>,
+    config: &AppConfig,
+    tx: &mut DbTransaction,
+) -> Result<chapter1::Allocated<chapter1::SplitAllocation>, ServiceError> {
+    let order = chapter1::OrderLine {
+        order_id: order_id.to_string(),
+        sku: sku.to_string(),
+        qty,
+    };
+
+    let product = load_product_for(tx, sku, region, config).await?;
+    if let Some(mut product) = product {
+        Ok(product.allocate_parts(&order)?)
     } else {
-        return Err(format!("Invalid sku {}", sku));
+        Err(format!("Invalid sku {}", sku).into())
     }
 }
 
+/// 解除訂單的所有分配（含拆分的每一部分），回傳釋放的 (批次, 數量)
+pub async fn deallocate(
+    order_id: &str,
+    sku: &str,
+    tx: &mut DbTransaction,
+) -> Result<Vec<(String, u32)>, ServiceError> {
+    let db = &mut *tx;
+
+    let product = load_product(&mut *db, sku).await?;
+    let Some(mut product) = product else {
+        return Err(format!("Invalid sku {}", sku).into());
+    };
+
+    let released = product.deallocate(order_id);
+    if released.is_empty() {
+        return Err(format!("Order {} is not allocated for sku {}", order_id, sku).into());
+    }
+
+    let line_clause = order_line_clause(order_id, sku);
+
+    delete::<&mut DbTransaction>(
+        &mut *db,
+        &Allocation::delete_sql(Some(&format!(
+            "order_line_id IN (SELECT id FROM {} WHERE {})",
+            OrderLine::table_name(),
+            line_clause
+        ))),
+    )
+    .await?;
+
+    delete::<&mut DbTransaction>(&mut *db, &OrderLine::delete_sql(Some(&line_clause))).await?;
+
+    save_version(sku, product.version_number, tx).await?;
+
+    Ok(released)
+}
+
+/// 以版本號做樂觀鎖：只有資料庫中的版本仍是讀取時的版本才會更新，
+/// 否則表示其他交易已先寫入，回傳 ConcurrencyConflict
+pub async fn save_version(
+    sku: &str,
+    version_number: i32,
+    tx: &mut DbTransaction,
+) -> Result<(), ServiceError> {
+    let updated = update::<&mut DbTransaction>(
+        &mut *tx,
+        &format!(
+            "UPDATE {} SET version_number = {}, updated_at = {} \
+             WHERE sku = {} AND version_number = {}",
+            Product::table_name(),
+            version_number,
+            quote(&Utc::now().to_rfc3339()),
+            quote(sku),
+            version_number - 1
+        ),
+    )
+    .await?;
+
+    if updated == 0 {
+        return Err(ServiceError::ConcurrencyConflict(format!(
+            "Version number conflict for sku {}",
+            sku
+        )));
+    }
+
+    Ok(())
+}
+
+/// 讀取商品聚合：商品、所有批次及已分配的訂單明細，使用預設的分配策略
+pub async fn load_product(
+    db: &mut DbTransaction,
+    sku: &str,
+) -> Result<Option<chapter1::Product>, sqlx::Error> {
+    let product_ent = read_one::<&mut DbTransaction, Product>(
+        &mut *db,
+        &Product::select_sql(Some(&format!("sku = {}", quote(sku)))),
+    )
+    .await?;
+
+    let Some(ent) = product_ent else {
+        return Ok(None);
+    };
+
+    let batches = load_batches(db, sku).await?;
+
+    Ok(Some(ent.build(batches)))
+}
+
+/// 讀取商品聚合並套用設定的分配策略；有目的地區域且設定了倉庫偏好時，優先分配最近倉庫的批次
+pub async fn load_product_for(
+    db: &mut DbTransaction,
+    sku: &str,
+    region: Option<&str>,
+    config: &AppConfig,
+) -> Result<Option<chapter1::Product>, sqlx::Error> {
+    let Some(product) = load_product(db, sku).await? else {
+        return Ok(None);
+    };
+
+    let mut strategy = config.allocation.strategy_for(sku).strategy();
+    if let Some(preference) = region.and_then(|r| config.warehouse.preference_for(r)) {
+        strategy = Box::new(chapter1::NearestWarehouse::new(preference, strategy));
+    }
+
+    Ok(Some(product.with_strategy(strategy)))
+}
+
+/// 讀取 sku 的所有批次，並帶入已分配的訂單明細（拆分時以 allocation 的數量為準）
+pub async fn load_batches(
+    db: &mut DbTransaction,
+    sku: &str,
+) -> Result<Vec<chapter1::Batch>, sqlx::Error> {
+    let where_clause = format!("sku = {}", quote(sku));
+    let batch_ents =
+        read::<&mut DbTransaction, Batch>(&mut *db, &Batch::select_sql(Some(&where_clause)))
+            .await?;
+
+    let mut batches = Vec::with_capacity(batch_ents.len());
+    for batch_ent in batch_ents {
+        let line_ents = read::<&mut DbTransaction, OrderLine>(
+            &mut *db,
+            &format!(
+                "SELECT o.id, o.order_id, o.sku, COALESCE(a.qty, o.qty) AS qty, \
+                 o.created_at, o.updated_at \
+                 FROM {} o JOIN {} a ON a.order_line_id = o.id WHERE a.batch_id = {}",
+                OrderLine::table_name(),
+                Allocation::table_name(),
+                quote(&batch_ent.id)
+            ),
+        )
+        .await?;
+
+        batches.push(batch_ent.build_with_lines(&line_ents));
+    }
+
+    Ok(batches)
+}
+
+/// 批次所在的倉庫
+pub async fn warehouse_of(
+    db: &mut DbTransaction,
+    reference: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    let batch_ent = read_one::<&mut DbTransaction, Batch>(
+        db,
+        &Batch::select_sql(Some(&format!("reference = {}", quote(reference)))),
+    )
+    .await?;
+
+    Ok(batch_ent.and_then(|b| b.warehouse))
+}
+
+/// 訂單在某 sku 的明細
+fn order_line_clause(order_id: &str, sku: &str) -> String {
+    format!("order_id = {} AND sku = {}", quote(order_id), quote(sku))
+}
+
+/// 寫入分配結果：訂單明細與每個 (批次, 數量) 各一筆 allocation 紀錄
+pub async fn save_allocation(
+    order_id: &str,
+    sku: &str,
+    qty: u32,
+    parts: &[(String, u32)],
+    tx: &mut DbTransaction,
+) -> Result<(), sqlx::Error> {
+    let db = &mut *tx;
+
+    let line_ent = read_one::<&mut DbTransaction, OrderLine>(
+        &mut *db,
+        &OrderLine::select_sql(Some(&order_line_clause(order_id, sku))),
+    )
+    .await?;
+
+    let line_ent = match line_ent {
+        Some(line_ent) => line_ent,
+        None => {
+            let line_ent = OrderLine {
+                id: xid::new().to_string(),
+                order_id: Some(order_id.to_string()),
+                sku: sku.to_string(),
+                qty,
+                created_at: chrono::Utc::now(),
+                updated_at: chrono::Utc::now(),
+            };
+
+            create::<&mut DbTransaction>(&mut *db, &line_ent.insert_sql()).await?;
+            line_ent
+        }
+    };
+
+    for (batch_ref, part_qty) in parts {
+        let batch_ent = read_one::<&mut DbTransaction, Batch>(
+            &mut *db,
+            &Batch::select_sql(Some(&format!("reference = {}", quote(batch_ref)))),
+        )
+        .await?
+        .ok_or(sqlx::Error::RowNotFound)?;
+
+        let allocation_ent = read_one::<&mut DbTransaction, Allocation>(
+            &mut *db,
+            &Allocation::select_sql(Some(&format!(
+                "batch_id = {} AND order_line_id = {}",
+                quote(&batch_ent.id),
+                quote(&line_ent.id)
+            ))),
+        )
+        .await?;
+
+        if allocation_ent.is_none() {
+            let allocation_ent = Allocation {
+                id: xid::new().to_string(),
+                batch_id: batch_ent.id.clone(),
+                order_line_id: line_ent.id.clone(),
+                qty: Some(*part_qty as i32),
+                created_at: chrono::Utc::now(),
+                updated_at: chrono::Utc::now(),
+            };
+
+            create::<&mut DbTransaction>(&mut *db, &allocation_ent.insert_sql()).await?;
+        }
+    }
+
+    Ok(())
+}
+
 pub async fn add_batch(
     reference: &str,
     sku: &str,
     quantity: u32,
     eta: Option<DateTime<Utc>>,
-    tx: &mut Transaction<'_, sqlx::Sqlite>,
+    warehouse: Option<&str>,
+    tx: &mut DbTransaction,
 ) -> Result<(), sqlx::Error> {
-    let db = &mut **tx;
+    let db = &mut *tx;
 
-    let where_clause_string = format!("sku = '{}'", sku);
+    let where_clause_string = format!("sku = {}", quote(sku));
     let where_clause = Some(where_clause_string.as_str());
 
     let product_ent =
-        read_one::<&mut SqliteConnection, Product>(db, &Product::select_sql(where_clause)).await?;
+        read_one::<&mut DbTransaction, Product>(db, &Product::select_sql(where_clause)).await?;
 
     if let Some(ent) = product_ent {
         let new_batch = chapter1::Batch::new(reference, sku, quantity, eta);
@@ -71,11 +407,13 @@ pub async fn add_batch(
             sku: sku.to_string(),
             qty: quantity,
             eta,
+            warehouse: warehouse.map(str::to_string),
+            arrived_at: None,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         };
 
-        create::<&mut SqliteConnection>(db, &batch_ent.insert_sql()).await?;
+        create::<&mut DbTransaction>(db, &batch_ent.insert_sql()).await?;
     } else {
         let new_batch = chapter1::Batch::new(reference, sku, quantity, eta);
         let _product = chapter1::Product::new(sku, vec![new_batch]);
@@ -88,7 +426,7 @@ pub async fn add_batch(
             updated_at: chrono::Utc::now(),
         };
 
-        create::<&mut SqliteConnection>(db, &ent.insert_sql()).await?;
+        create::<&mut DbTransaction>(db, &ent.insert_sql()).await?;
 
         let batch_ent = Batch {
             id: xid::new().to_string(),
@@ -96,12 +434,363 @@ pub async fn add_batch(
             sku: sku.to_string(),
             qty: quantity,
             eta,
+            warehouse: warehouse.map(str::to_string),
+            arrived_at: None,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         };
 
-        create::<&mut SqliteConnection>(db, &batch_ent.insert_sql()).await?;
+        create::<&mut DbTransaction>(db, &batch_ent.insert_sql()).await?;
+    }
+
+    Ok(())
+}
+
+/// 到貨：ETA 已過的批次清除 ETA 並記錄到貨時間，之後即視為庫存
+pub async fn arrive_batches(
+    now: DateTime<Utc>,
+    tx: &mut DbTransaction,
+) -> Result<Vec<events::BatchArrived>, String> {
+    let db = &mut *tx;
+
+    let shipments = read::<&mut DbTransaction, Batch>(
+        &mut *db,
+        &Batch::select_sql(Some("eta IS NOT NULL AND arrived_at IS NULL")),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut arrived = Vec::new();
+    for batch_ent in shipments
+        .into_iter()
+        .filter(|b| b.eta.is_some_and(|eta| eta <= now))
+    {
+        update::<&mut DbTransaction>(
+            &mut *db,
+            &format!(
+                "UPDATE {} SET eta = NULL, arrived_at = {}, updated_at = {} WHERE id = {}",
+                Batch::table_name(),
+                quote(&now.to_rfc3339()),
+                quote(&Utc::now().to_rfc3339()),
+                quote(&batch_ent.id)
+            ),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+        arrived.push(events::BatchArrived {
+            reference: batch_ent.reference,
+            sku: batch_ent.sku,
+            qty: batch_ent.qty,
+        });
+    }
+
+    Ok(arrived)
+}
+
+/// 將較晚到貨批次上的訂單明細改分配到已到貨的批次，從最晚的批次開始搬移，
+/// 回傳搬移的 (訂單, 原批次)
+pub async fn reallocate_to_arrived(
+    reference: &str,
+    tx: &mut DbTransaction,
+) -> Result<Vec<(String, String)>, String> {
+    let db = &mut *tx;
+
+    let arrived = read_one::<&mut DbTransaction, Batch>(
+        &mut *db,
+        &Batch::select_sql(Some(&format!("reference = {}", quote(reference)))),
+    )
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("Invalid batch {}", reference))?;
+
+    let mut available = load_batches(&mut *db, &arrived.sku)
+        .await
+        .map_err(|e| e.to_string())?
+        .iter()
+        .find(|b| b.reference == arrived.reference)
+        .map(|b| b.available_quantity())
+        .unwrap_or(0);
+
+    // (allocation id, order_line id, 訂單, 數量, 原批次)
+    let candidates = read::<&mut DbTransaction, (String, String, String, i32, String)>(
+        &mut *db,
+        &format!(
+            "SELECT a.id, o.id, COALESCE(o.order_id, o.id), COALESCE(a.qty, o.qty), b.reference \
+             FROM {} a \
+             JOIN {} o ON o.id = a.order_line_id \
+             JOIN {} b ON b.id = a.batch_id \
+             WHERE b.sku = {} AND b.eta IS NOT NULL \
+             ORDER BY b.eta DESC, a.created_at",
+            Allocation::table_name(),
+            OrderLine::table_name(),
+            Batch::table_name(),
+            quote(&arrived.sku)
+        ),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut moved = Vec::new();
+    for (allocation_id, order_line_id, order_id, qty, from_ref) in candidates {
+        let qty = qty as u32;
+        if qty > available {
+            continue;
+        }
+
+        // 同一明細已有部分分配在到貨批次時不搬移，避免同批次出現兩筆分配
+        let existing = read_one::<&mut DbTransaction, Allocation>(
+            &mut *db,
+            &Allocation::select_sql(Some(&format!(
+                "batch_id = {} AND order_line_id = {}",
+                quote(&arrived.id),
+                quote(&order_line_id)
+            ))),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+        if existing.is_some() {
+            continue;
+        }
+
+        update::<&mut DbTransaction>(
+            &mut *db,
+            &format!(
+                "UPDATE {} SET batch_id = {}, updated_at = {} WHERE id = {}",
+                Allocation::table_name(),
+                quote(&arrived.id),
+                quote(&Utc::now().to_rfc3339()),
+                quote(&allocation_id)
+            ),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+        available -= qty;
+        moved.push((order_id, from_ref));
+    }
+
+    if !moved.is_empty() {
+        update::<&mut DbTransaction>(
+            db,
+            &format!(
+                "UPDATE {} SET version_number = version_number + 1, updated_at = {} WHERE sku = {}",
+                Product::table_name(),
+                quote(&Utc::now().to_rfc3339()),
+                quote(&arrived.sku)
+            ),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(moved)
+}
+
+/// 保留庫存：分配並記錄到期時間，到期前未確認會由 sweeper 釋放
+pub async fn reserve(
+    order_id: &str,
+    sku: &str,
+    qty: u32,
+    expires_at: DateTime<Utc>,
+    config: &AppConfig,
+    tx: &mut DbTransaction,
+) -> Result<String, ServiceError> {
+    let held = read_one::<&mut DbTransaction, Reservation>(
+        &mut *tx,
+        &Reservation::select_sql(Some(&format!(
+            "{} AND status = {}",
+            order_line_clause(order_id, sku),
+            quote(reservations::HELD)
+        ))),
+    )
+    .await?;
+    if held.is_some() {
+        return Err(format!(
+            "Order {} already holds a reservation for sku {}",
+            order_id, sku
+        )
+        .into());
+    }
+
+    // 已分配的訂單不能再保留，否則到期時會釋放不屬於保留的分配
+    let line_ent = read_one::<&mut DbTransaction, OrderLine>(
+        &mut *tx,
+        &OrderLine::select_sql(Some(&order_line_clause(order_id, sku))),
+    )
+    .await?;
+    if line_ent.is_some() {
+        return Err(format!("Order {} is already allocated for sku {}", order_id, sku).into());
+    }
+
+    let allocated = allocate_order(order_id, &[(sku.to_string(), qty)], None, config, tx).await?;
+
+    let allocation_ent = read_one::<&mut DbTransaction, Allocation>(
+        &mut *tx,
+        &Allocation::select_sql(Some(&format!(
+            "order_line_id IN (SELECT id FROM {} WHERE {})",
+            OrderLine::table_name(),
+            order_line_clause(order_id, sku)
+        ))),
+    )
+    .await?;
+
+    let reservation = Reservation {
+        id: xid::new().to_string(),
+        order_id: order_id.to_string(),
+        sku: sku.to_string(),
+        qty,
+        status: reservations::HELD.to_string(),
+        allocation_id: allocation_ent.map(|a| a.id),
+        expires_at,
+        created_at: chrono::Utc::now(),
+        updated_at: chrono::Utc::now(),
+    };
+
+    create::<&mut DbTransaction>(&mut *tx, &reservation.insert_sql()).await?;
+
+    Ok(allocated[0].1.clone())
+}
+
+/// 確認保留：保留中的分配轉為正式分配，不再受到期釋放影響
+pub async fn confirm_reservation(
+    order_id: &str,
+    sku: &str,
+    tx: &mut DbTransaction,
+) -> Result<(), String> {
+    let db = &mut *tx;
+
+    let reservation = read_one::<&mut DbTransaction, Reservation>(
+        &mut *db,
+        &Reservation::select_sql(Some(&format!(
+            "{} AND status = {}",
+            order_line_clause(order_id, sku),
+            quote(reservations::HELD)
+        ))),
+    )
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("No reservation for order {} and sku {}", order_id, sku))?;
+
+    if reservation.is_expired(Utc::now()) {
+        return Err(format!(
+            "Reservation for order {} and sku {} has expired",
+            order_id, sku
+        ));
+    }
+
+    // 分配已經以 /deallocate 釋放時沒有可確認的庫存
+    if reserved_allocation(db, &reservation)
+        .await
+        .map_err(|e| e.to_string())?
+        .is_none()
+    {
+        return Err(format!(
+            "Reservation for order {} and sku {} no longer holds an allocation",
+            order_id, sku
+        ));
+    }
+
+    set_reservation_status(db, &reservation.id, reservations::CONFIRMED)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 列出已到期但仍保留中的保留，由呼叫端逐筆釋放
+pub async fn expired_reservations(
+    now: DateTime<Utc>,
+    db: &DbPool,
+) -> Result<Vec<Reservation>, sqlx::Error> {
+    let held = read::<&DbPool, Reservation>(
+        db,
+        &Reservation::select_sql(Some(&format!("status = {}", quote(reservations::HELD)))),
+    )
+    .await?;
+
+    Ok(held.into_iter().filter(|r| r.is_expired(now)).collect())
+}
+
+/// 保留建立的 allocation；已被釋放或舊資料沒有記錄時回傳 None
+async fn reserved_allocation(
+    db: &mut DbTransaction,
+    reservation: &Reservation,
+) -> Result<Option<Allocation>, sqlx::Error> {
+    let Some(allocation_id) = &reservation.allocation_id else {
+        return Ok(None);
+    };
+
+    read_one::<&mut DbTransaction, Allocation>(
+        db,
+        &Allocation::select_sql(Some(&format!("id = {}", quote(allocation_id)))),
+    )
+    .await
+}
+
+/// 釋放單一到期保留：只刪除保留建立的那筆 allocation，訂單的其他分配不受影響。
+/// 分配已先被釋放時只將保留標記為到期，不回傳事件
+pub async fn expire_reservation(
+    reservation: &Reservation,
+    tx: &mut DbTransaction,
+) -> Result<Option<events::ReservationExpired>, ServiceError> {
+    let allocation = reserved_allocation(tx, reservation).await?;
+
+    if let Some(allocation) = &allocation {
+        let product = load_product(tx, &reservation.sku).await?;
+        let Some(product) = product else {
+            return Err(format!("Invalid sku {}", reservation.sku).into());
+        };
+
+        delete::<&mut DbTransaction>(
+            &mut *tx,
+            &Allocation::delete_sql(Some(&format!("id = {}", quote(&allocation.id)))),
+        )
+        .await?;
+
+        // 沒有其他分配的訂單明細一併刪除
+        delete::<&mut DbTransaction>(
+            &mut *tx,
+            &OrderLine::delete_sql(Some(&format!(
+                "id = {} AND NOT EXISTS (SELECT 1 FROM {} WHERE order_line_id = {})",
+                quote(&allocation.order_line_id),
+                Allocation::table_name(),
+                quote(&allocation.order_line_id)
+            ))),
+        )
+        .await?;
+
+        save_version(&reservation.sku, product.version_number, tx).await?;
+    } else {
+        tracing::warn!(
+            "Reservation {} no longer holds an allocation, marking it expired",
+            reservation.id
+        );
     }
 
+    set_reservation_status(tx, &reservation.id, reservations::EXPIRED).await?;
+
+    Ok(allocation.map(|_| events::ReservationExpired {
+        order_id: reservation.order_id.clone(),
+        sku: reservation.sku.clone(),
+        qty: reservation.qty,
+    }))
+}
+
+async fn set_reservation_status(
+    db: &mut DbTransaction,
+    id: &str,
+    status: &str,
+) -> Result<(), sqlx::Error> {
+    update::<&mut DbTransaction>(
+        db,
+        &format!(
+            "UPDATE {} SET status = {}, updated_at = {} WHERE id = {}",
+            Reservation::table_name(),
+            quote(status),
+            quote(&Utc::now().to_rfc3339()),
+            quote(id)
+        ),
+    )
+    .await?;
+
     Ok(())
 }
@@ -1,6 +1,14 @@
-use sqlx::SqlitePool;
+use std::sync::Arc;
+
+use crate::repositories::DbPool;
+
+use crate::configures::{AppConfig, RuntimeConfig};
+use crate::health::Workers;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub db: SqlitePool,
+    pub db: DbPool,
+    pub config: Arc<AppConfig>,
+    pub runtime: RuntimeConfig,
+    pub workers: Workers,
 }
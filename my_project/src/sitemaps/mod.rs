@@ -1,65 +1,216 @@
 pub mod app_state;
 
+use std::sync::Arc;
+
 use axum::Router;
+use axum::body::Body;
+use axum::extract::State;
 use axum::http::StatusCode;
-use axum::http::{Request, Uri};
-use axum::response::IntoResponse;
+use axum::http::{HeaderName, HeaderValue, Method, Request, Uri, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
 
-use sqlx::SqlitePool;
+use crate::repositories::DbPool;
 
+use tower::{Layer, ServiceExt};
 use tower_http::compression::CompressionLayer;
-use tower_http::cors::CorsLayer;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::sensitive_headers::SetSensitiveRequestHeadersLayer;
 use tower_http::timeout::TimeoutLayer;
 use tower_http::trace::{DefaultOnResponse, TraceLayer};
 
+use crate::admin;
 use crate::api_base::api_errors;
+use crate::auth;
 use crate::chapter3;
+use crate::configures::{AppConfig, RuntimeConfig, ServerConfig};
+use crate::events;
+use crate::health::{self, Workers};
+use crate::metrics;
 use crate::sitemaps::app_state::AppState;
 
-pub async fn sitemap(db: SqlitePool) -> Router {
-    let app_state = AppState { db: db.clone() };
+pub async fn sitemap(
+    db: DbPool,
+    config: Arc<AppConfig>,
+    runtime: RuntimeConfig,
+    workers: Workers,
+) -> Router {
+    let app_state = AppState {
+        db: db.clone(),
+        config,
+        runtime,
+        workers,
+    };
 
     let compression = CompressionLayer::new();
 
-    let cors = CorsLayer::new()
-        .allow_origin(tower_http::cors::Any)
-        .allow_methods(tower_http::cors::Any)
-        .allow_headers(tower_http::cors::Any)
-        .allow_credentials(false)
-        .max_age(std::time::Duration::from_secs(3600 * 12));
-
-    let timeout = TimeoutLayer::with_status_code(
-        StatusCode::REQUEST_TIMEOUT,
-        std::time::Duration::from_secs(10),
-    );
+    let runtime_layers =
+        axum::middleware::from_fn_with_state(app_state.runtime.clone(), runtime_layers);
 
     let trace = TraceLayer::new_for_http()
-        .make_span_with(|request: &Request<axum::body::Body>| {
+        .make_span_with(|request: &Request<Body>| {
             let method = request.method();
             let path = request.uri().path();
             let headers = request.headers();
+            // SetRequestIdLayer 在外層，這裡一定拿得到 id
+            let request_id = headers
+                .get(REQUEST_ID)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default();
 
             tracing::info_span!(
                 "sitemap",
                 headers = ?headers,
                 method = %method,
                 uri = %path,
+                request_id = %request_id,
+                sku = tracing::field::Empty,
+                order_id = tracing::field::Empty,
+                api_key_id = tracing::field::Empty,
             )
         })
         .on_failure(())
         .on_request(())
         .on_response(DefaultOnResponse::new().level(tracing::Level::INFO));
 
+    // 寫入與維運的 route 需要 access token，查詢、換發 token 與探測不需要
+    let protected = chapter3::logic_routes()
+        .merge(admin::admin_routes())
+        .route_layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            auth::authenticate,
+        ));
+
     Router::new()
-        .merge(chapter3::logic_routes())
+        .merge(protected)
+        .merge(chapter3::view_routes())
+        .merge(auth::auth_routes())
+        .route_layer(axum::middleware::from_fn(metrics::track_http))
         .layer(trace)
-        .layer(timeout)
-        .layer(cors)
+        .merge(health::health_routes())
+        .merge(metrics::metrics_routes())
+        .layer(runtime_layers)
         .layer(compression)
+        .layer(axum::middleware::from_fn(scope_request_id))
+        .layer(PropagateRequestIdLayer::new(REQUEST_ID))
+        .layer(SetSensitiveRequestHeadersLayer::from_shared(
+            SENSITIVE_HEADERS.into(),
+        ))
+        .layer(SetRequestIdLayer::new(REQUEST_ID, MakeRequestUuid))
+        .layer(axum::middleware::from_fn(drop_invalid_request_id))
         .fallback(fallback)
         .with_state(app_state.clone())
 }
 
+/// 上游帶入的 request id，沒有時自行產生，並回傳在 response header
+pub const REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// 在 span 的 headers 中只顯示為 `Sensitive`
+const SENSITIVE_HEADERS: [HeaderName; 4] = [
+    header::AUTHORIZATION,
+    header::PROXY_AUTHORIZATION,
+    header::COOKIE,
+    HeaderName::from_static("x-api-key"),
+];
+
+/// 上游的 request id 會寫進 log 與事件，只接受長度有限的英數字與 `-_.:`
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+pub fn is_valid_request_id(id: &str) -> bool {
+    (1..=MAX_REQUEST_ID_LEN).contains(&id.len())
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':'))
+}
+
+/// 移除不合法的 request id，改由 `SetRequestIdLayer` 重新產生
+async fn drop_invalid_request_id(mut request: Request<Body>, next: Next) -> Response {
+    let invalid = request
+        .headers()
+        .get(REQUEST_ID)
+        .is_some_and(|value| !value.to_str().is_ok_and(is_valid_request_id));
+    if invalid {
+        request.headers_mut().remove(REQUEST_ID);
+    }
+    next.run(request).await
+}
+
+/// 讓這個 request 發出的 message bus 事件帶著同一個 request id
+pub async fn scope_request_id(request: Request<Body>, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map(str::to_string);
+
+    match request_id {
+        Some(request_id) => {
+            events::REQUEST_ID
+                .scope(request_id, next.run(request))
+                .await
+        }
+        None => next.run(request).await,
+    }
+}
+
+/// 將 sku、order id 記到目前的 request span，之後每一行 log 都會帶上
+pub fn record_span(sku: Option<&str>, order_id: Option<&str>) {
+    let span = tracing::Span::current();
+    if let Some(sku) = sku {
+        span.record("sku", sku);
+    }
+    if let Some(order_id) = order_id {
+        span.record("order_id", order_id);
+    }
+}
+
 async fn fallback(uri: Uri) -> impl IntoResponse {
     api_errors::ApiError::BadRequest(format!("No route found for {}", uri)).into_response()
 }
+
+/// CORS 與 timeout 每次依目前的執行期設定建立，設定檔重新載入後立即生效
+async fn runtime_layers(
+    State(runtime): State<RuntimeConfig>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let server = runtime.server();
+    let timeout =
+        TimeoutLayer::with_status_code(StatusCode::REQUEST_TIMEOUT, server.request_timeout());
+
+    match cors(&server)
+        .layer(timeout.layer(next))
+        .oneshot(request)
+        .await
+    {
+        Ok(response) => response,
+        Err(never) => match never {},
+    }
+}
+
+/// 開發與測試環境允許任何來源；正式環境只允許 `server.cors_origins` 白名單
+fn cors(server: &ServerConfig) -> CorsLayer {
+    let cors = CorsLayer::new()
+        .allow_credentials(false)
+        .expose_headers([REQUEST_ID])
+        .max_age(std::time::Duration::from_secs(3600 * 12));
+
+    if !server.profile().is_production() {
+        return cors
+            .allow_origin(tower_http::cors::Any)
+            .allow_methods(tower_http::cors::Any)
+            .allow_headers(tower_http::cors::Any);
+    }
+
+    let origins: Vec<HeaderValue> = server
+        .cors_origins()
+        .iter()
+        .filter_map(|origin| origin.parse().ok())
+        .collect();
+
+    cors.allow_origin(AllowOrigin::list(origins))
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+        .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION, REQUEST_ID])
+}
@@ -0,0 +1,143 @@
+use crate::repositories::DbPool;
+use serde_json::{Value as JsonValue, json};
+
+use crate::entities::{
+    allocations::Allocation, batches::Batch, order_lines::OrderLine, products::Product,
+};
+use crate::repositories::{quote, read_one_to_json, read_to_json};
+
+pub const DEFAULT_PER_PAGE: u32 = 20;
+pub const MAX_PER_PAGE: u32 = 100;
+
+/// 批次數量查詢：進貨量、已分配量、可用量
+fn batch_quantities_sql(where_clause: &str) -> String {
+    format!(
+        "SELECT b.reference, b.sku, b.eta, b.warehouse, b.qty AS purchased_quantity, \
+         COALESCE(SUM(COALESCE(a.qty, o.qty)), 0) AS allocated_quantity, \
+         b.qty - COALESCE(SUM(COALESCE(a.qty, o.qty)), 0) AS available_quantity \
+         FROM {} b \
+         LEFT JOIN {} a ON a.batch_id = b.id \
+         LEFT JOIN {} o ON o.id = a.order_line_id \
+         WHERE {} \
+         GROUP BY b.id \
+         ORDER BY b.eta IS NOT NULL, b.eta, b.reference",
+        Batch::table_name(),
+        Allocation::table_name(),
+        OrderLine::table_name(),
+        where_clause
+    )
+}
+
+/// 商品版本與其所有批次的數量
+pub async fn product(db: &DbPool, sku: &str) -> Result<Option<JsonValue>, sqlx::Error> {
+    let product = read_one_to_json(
+        db,
+        &format!(
+            "SELECT sku, MAX(version_number) AS version_number FROM {} WHERE sku = {} GROUP BY sku",
+            Product::table_name(),
+            quote(sku)
+        ),
+    )
+    .await?;
+
+    let Some(mut product) = product else {
+        return Ok(None);
+    };
+
+    let batches = read_to_json(
+        db,
+        &batch_quantities_sql(&format!("b.sku = {}", quote(sku))),
+    )
+    .await?;
+    product["batches"] = JsonValue::Array(batches);
+
+    Ok(Some(product))
+}
+
+/// 批次數量與已分配的訂單明細
+pub async fn batch(db: &DbPool, reference: &str) -> Result<Option<JsonValue>, sqlx::Error> {
+    let batch = read_one_to_json(
+        db,
+        &batch_quantities_sql(&format!("b.reference = {}", quote(reference))),
+    )
+    .await?;
+
+    let Some(mut batch) = batch else {
+        return Ok(None);
+    };
+
+    let allocations = read_to_json(
+        db,
+        &format!(
+            "SELECT o.order_id, o.sku, COALESCE(a.qty, o.qty) AS qty FROM {} o \
+             JOIN {} a ON a.order_line_id = o.id \
+             JOIN {} b ON b.id = a.batch_id \
+             WHERE b.reference = {} \
+             ORDER BY a.created_at, o.order_id",
+            OrderLine::table_name(),
+            Allocation::table_name(),
+            Batch::table_name(),
+            quote(reference)
+        ),
+    )
+    .await?;
+    batch["allocations"] = JsonValue::Array(allocations);
+
+    Ok(Some(batch))
+}
+
+/// 商品列表，依 sku 排序並分頁，可用 sku 前綴過濾
+pub async fn products(
+    db: &DbPool,
+    page: u32,
+    per_page: u32,
+    sku_prefix: Option<&str>,
+) -> Result<JsonValue, sqlx::Error> {
+    let page = page.max(1);
+    let per_page = per_page.clamp(1, MAX_PER_PAGE);
+
+    let where_clause = match sku_prefix {
+        Some(prefix) => {
+            // LIKE 的萬用字元需跳脫
+            let pattern = prefix
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            format!("sku LIKE {} ESCAPE '\\'", quote(&format!("{}%", pattern)))
+        }
+        None => "1=1".to_string(),
+    };
+
+    let items = read_to_json(
+        db,
+        &format!(
+            "SELECT sku, MAX(version_number) AS version_number FROM {} WHERE {} \
+             GROUP BY sku ORDER BY sku LIMIT {} OFFSET {}",
+            Product::table_name(),
+            where_clause,
+            per_page,
+            // 以 u64 計算，極大的頁碼不會溢位
+            u64::from(page - 1) * u64::from(per_page)
+        ),
+    )
+    .await?;
+
+    let total = read_one_to_json(
+        db,
+        &format!(
+            "SELECT COUNT(DISTINCT sku) AS total FROM {} WHERE {}",
+            Product::table_name(),
+            where_clause
+        ),
+    )
+    .await?
+    .and_then(|row| row.get("total").cloned())
+    .unwrap_or(JsonValue::from(0));
+
+    Ok(json!({
+        "items": items,
+        "page": page,
+        "per_page": per_page,
+        "total": total,
+    }))
+}
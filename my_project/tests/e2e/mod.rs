@@ -1 +1,9 @@
+pub mod test_admin;
 pub mod test_api;
+pub mod test_api_keys;
+pub mod test_auth;
+pub mod test_concurrency;
+pub mod test_health;
+pub mod test_logging;
+pub mod test_metrics;
+pub mod test_request_id;
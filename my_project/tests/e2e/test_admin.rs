@@ -0,0 +1,79 @@
+use architecture::configures::{AppConfig, ServerConfig};
+use serde_json::json;
+
+use crate::support::TestApp;
+
+#[tokio::test]
+async fn test_put_log_level_changes_filter_directive() {
+    let app = TestApp::new().await;
+
+    let (status, body) = app.get_json("/admin/log-level").await;
+    assert_eq!(status, 200);
+    assert_eq!(body["directive"], "info");
+
+    let (status, body) = app
+        .request(
+            "PUT",
+            "/admin/log-level",
+            Some(json!({ "directive": "debug,sqlx=warn" })),
+        )
+        .await;
+    assert_eq!(status, 200);
+    assert_eq!(body["directive"], "debug,sqlx=warn");
+    assert_eq!(app.runtime.log_level().current(), "debug,sqlx=warn");
+
+    let (status, body) = app
+        .request(
+            "PUT",
+            "/admin/log-level",
+            Some(json!({ "directive": "verbose" })),
+        )
+        .await;
+    assert_eq!(status, 400);
+    assert_eq!(body["message"], "Invalid log level verbose");
+    assert_eq!(app.runtime.log_level().current(), "debug,sqlx=warn");
+}
+
+#[tokio::test]
+async fn test_reloaded_cors_origins_apply_without_restart() {
+    let server = ServerConfig {
+        env: Some("production".to_string()),
+        cors_origins: Some(vec!["https://shop.example.com".to_string()]),
+        ..Default::default()
+    };
+    let app = TestApp::with_config(AppConfig::builder().server(server.clone()).build()).await;
+
+    let preflight = || {
+        axum::extract::Request::builder()
+            .method("OPTIONS")
+            .uri("/allocate")
+            .header("Origin", "https://admin.example.com")
+            .header("Access-Control-Request-Method", "POST")
+            .body(axum::body::Body::empty())
+            .unwrap()
+    };
+    let res = app.send(preflight()).await;
+    assert!(!res.headers().contains_key("access-control-allow-origin"));
+
+    let changed = app
+        .runtime
+        .apply(
+            &AppConfig::builder()
+                .server(ServerConfig {
+                    cors_origins: Some(vec!["https://admin.example.com".to_string()]),
+                    port: Some(9999),
+                    ..server
+                })
+                .build(),
+        )
+        .unwrap();
+    assert_eq!(changed, vec!["server.cors_origins"]);
+
+    let res = app.send(preflight()).await;
+    assert_eq!(
+        res.headers()["access-control-allow-origin"],
+        "https://admin.example.com"
+    );
+    // port 需要重新啟動才會生效
+    assert_eq!(app.runtime.server().port, None);
+}
@@ -1,9 +1,12 @@
-use architecture::{configures, entities::order_lines};
-use axum::{body::Body, extract::Request};
+use std::collections::HashMap;
+
+use architecture::configures::{AppConfig, ServerConfig, WarehouseConfig};
+use architecture::entities::order_lines;
+use architecture::{events, messagebus};
 use chrono::Utc;
-use http_body_util::BodyExt;
 use serde_json::Value;
-use tower::ServiceExt;
+
+use crate::support::TestApp;
 
 fn random_suffix() -> String {
     let s = xid::new().to_string();
@@ -26,78 +29,59 @@ fn random_order_id(name: &str) -> String {
     format!("order-{}-{}", name, random_suffix())
 }
 
-async fn post_to_add_batch(refe: &str, sku: &str, qty: u32, eta: Option<String>) {
-    let db = configures::AppConfig::load()
-        .database
-        .get_connection()
+async fn post_to_add_batch(app: &TestApp, refe: &str, sku: &str, qty: u32, eta: Option<String>) {
+    let (status, _) = app
+        .post_json(
+            "/add_batch",
+            serde_json::json!({ "reference": refe, "sku": sku, "qty": qty, "eta": eta }),
+        )
         .await;
-    let route = architecture::sitemaps::sitemap(db).await;
-
-    let mut map = serde_json::Map::new();
-    map.insert("reference".to_string(), Value::String(refe.to_string()));
-    map.insert("sku".to_string(), Value::String(sku.to_string()));
-    map.insert("qty".to_string(), Value::Number(qty.into()));
-    if let Some(e) = eta {
-        map.insert("eta".to_string(), Value::String(e.to_string()));
-    } else {
-        map.insert("eta".to_string(), Value::Null);
-    }
-
-    let request = Request::builder()
-        .method("POST")
-        .uri("/add_batch")
-        .header("Content-Type", "application/json")
-        .body(Body::from(serde_json::to_string(&map).unwrap()))
-        .unwrap();
-
-    let res = route.oneshot(request).await.unwrap();
-
-    let status = res.status();
     assert_eq!(status, 201);
 }
 
 #[tokio::test]
 async fn test_api_returns_allocation() {
-    let db = configures::AppConfig::load()
-        .database
-        .get_connection()
-        .await;
+    let app = TestApp::new().await;
 
     let sku = random_sku("");
     let other_sku = random_sku("OTHER");
 
     let early_batch_ref = random_batch_ref("1");
-    post_to_add_batch(&early_batch_ref, &sku, 100, Some("2011-01-02".to_string())).await;
+    post_to_add_batch(
+        &app,
+        &early_batch_ref,
+        &sku,
+        100,
+        Some("2011-01-01".to_string()),
+    )
+    .await;
     let later_batch_ref = random_batch_ref("2");
-    post_to_add_batch(&later_batch_ref, &sku, 100, Some("2011-01-01".to_string())).await;
+    post_to_add_batch(
+        &app,
+        &later_batch_ref,
+        &sku,
+        100,
+        Some("2011-01-02".to_string()),
+    )
+    .await;
     let other_batch_ref = random_batch_ref("3");
-    post_to_add_batch(&other_batch_ref, &other_sku, 100, None).await;
+    post_to_add_batch(&app, &other_batch_ref, &other_sku, 100, None).await;
 
     let data = order_lines::OrderLine {
         id: random_order_id(""),
+        order_id: None,
         sku: sku.clone(),
         qty: 3,
         created_at: Utc::now(),
         updated_at: Utc::now(),
     };
 
-    let route = architecture::sitemaps::sitemap(db).await;
-
-    let request = Request::builder()
-        .method("POST")
-        .uri("/allocate")
-        .header("Content-Type", "application/json")
-        .body(Body::from(serde_json::to_string(&data).unwrap()))
-        .unwrap();
-
-    let res = route.oneshot(request).await.unwrap();
-
-    let status = res.status();
+    let (status, body) = app
+        .post_json("/allocate", serde_json::to_value(&data).unwrap())
+        .await;
     assert_eq!(status, 201);
 
-    let body = res.into_body().collect().await.unwrap().to_bytes();
-    let body_str = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
-    let batch_ref = body_str.get("batch_ref").unwrap().as_str().unwrap();
+    let batch_ref = body.get("batch_ref").unwrap().as_str().unwrap();
     assert_eq!(batch_ref, early_batch_ref);
 }
 
@@ -106,34 +90,575 @@ async fn test_400_message_for_invalid_sku() {
     let unknown_sku = random_sku("");
     let order_id = random_order_id("");
 
-    let db = configures::AppConfig::load()
-        .database
-        .get_connection()
-        .await;
+    let app = TestApp::new().await;
 
     let data = order_lines::OrderLine {
         id: order_id.clone(),
+        order_id: None,
         sku: unknown_sku.clone(),
         qty: 20,
         created_at: Utc::now(),
         updated_at: Utc::now(),
     };
 
-    let route = architecture::sitemaps::sitemap(db).await;
+    let (status, body) = app
+        .post_json("/allocate", serde_json::to_value(&data).unwrap())
+        .await;
+    assert_eq!(status, 400);
 
-    let request = Request::builder()
-        .method("POST")
-        .uri("/allocate")
-        .header("Content-Type", "application/json")
-        .body(Body::from(serde_json::to_string(&data).unwrap()))
+    let message = body.get("message").unwrap().as_str().unwrap();
+    assert_eq!(message, format!("Invalid sku {}", unknown_sku));
+}
+
+async fn post_to_allocate(app: &TestApp, order_id: &str, sku: &str, qty: u32) -> String {
+    let (status, body) = app
+        .post_json(
+            "/allocate",
+            serde_json::json!({ "id": order_id, "sku": sku, "qty": qty }),
+        )
+        .await;
+    assert_eq!(status, 201);
+
+    body["batch_ref"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_get_product_returns_batch_quantities() {
+    let app = TestApp::new().await;
+    let sku = random_sku("VIEW");
+    let in_stock_ref = random_batch_ref("1");
+    let shipment_ref = random_batch_ref("2");
+    post_to_add_batch(&app, &in_stock_ref, &sku, 20, None).await;
+    post_to_add_batch(
+        &app,
+        &shipment_ref,
+        &sku,
+        50,
+        Some("2011-01-01".to_string()),
+    )
+    .await;
+
+    let batch_ref = post_to_allocate(&app, &random_order_id(""), &sku, 5).await;
+
+    let (status, product) = app.get_json(&format!("/products/{}", sku)).await;
+    assert_eq!(status, 200);
+    assert_eq!(product["sku"], sku);
+    assert!(product["version_number"].as_i64().is_some());
+
+    let batches = product["batches"].as_array().unwrap();
+    assert_eq!(batches.len(), 2);
+
+    let allocated = batches
+        .iter()
+        .find(|b| b["reference"] == batch_ref.as_str())
+        .unwrap();
+    let purchased = allocated["purchased_quantity"].as_i64().unwrap();
+    assert_eq!(allocated["allocated_quantity"], 5);
+    assert_eq!(allocated["available_quantity"], purchased - 5);
+
+    let untouched = batches
+        .iter()
+        .find(|b| b["reference"] != batch_ref.as_str())
         .unwrap();
+    assert_eq!(untouched["allocated_quantity"], 0);
+    assert_eq!(
+        untouched["available_quantity"],
+        untouched["purchased_quantity"]
+    );
+}
+
+#[tokio::test]
+async fn test_get_batch_returns_allocated_order_lines() {
+    let app = TestApp::new().await;
+    let sku = random_sku("VIEW");
+    let batch_ref = random_batch_ref("");
+    post_to_add_batch(&app, &batch_ref, &sku, 100, None).await;
+
+    let order1 = random_order_id("1");
+    let order2 = random_order_id("2");
+    post_to_allocate(&app, &order1, &sku, 10).await;
+    post_to_allocate(&app, &order2, &sku, 15).await;
 
-    let response = route.clone().oneshot(request).await.unwrap();
-    let status = response.status();
+    let (status, batch) = app.get_json(&format!("/batches/{}", batch_ref)).await;
+    assert_eq!(status, 200);
+    assert_eq!(batch["reference"], batch_ref);
+    assert_eq!(batch["purchased_quantity"], 100);
+    assert_eq!(batch["allocated_quantity"], 25);
+    assert_eq!(batch["available_quantity"], 75);
+
+    let mut order_ids = batch["allocations"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|line| line["order_id"].as_str().unwrap().to_string())
+        .collect::<Vec<String>>();
+    order_ids.sort();
+    let mut expected = vec![order1, order2];
+    expected.sort();
+    assert_eq!(order_ids, expected);
+}
+
+#[tokio::test]
+async fn test_list_products_filters_by_prefix_and_paginates() {
+    let app = TestApp::new().await;
+    let prefix = format!("sku-LIST{}-", random_suffix());
+    for name in ["a", "b", "c"] {
+        post_to_add_batch(
+            &app,
+            &random_batch_ref(name),
+            &format!("{}{}", prefix, name),
+            10,
+            None,
+        )
+        .await;
+    }
+
+    let (status, page1) = app
+        .get_json(&format!("/products?sku_prefix={}&per_page=2", prefix))
+        .await;
+    assert_eq!(status, 200);
+    assert_eq!(page1["total"], 3);
+    assert_eq!(page1["page"], 1);
+    let skus = page1["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["sku"].as_str().unwrap().to_string())
+        .collect::<Vec<String>>();
+    assert_eq!(skus, vec![format!("{}a", prefix), format!("{}b", prefix)]);
+
+    let (_, page2) = app
+        .get_json(&format!(
+            "/products?sku_prefix={}&per_page=2&page=2",
+            prefix
+        ))
+        .await;
+    let skus = page2["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["sku"].as_str().unwrap().to_string())
+        .collect::<Vec<String>>();
+    assert_eq!(skus, vec![format!("{}c", prefix)]);
+
+    // 超出範圍的頁碼回傳空頁，不會溢位
+    let (status, last) = app
+        .get_json(&format!(
+            "/products?sku_prefix={}&per_page=100&page={}",
+            prefix,
+            u32::MAX
+        ))
+        .await;
+    assert_eq!(status, 200);
+    assert_eq!(last["items"], serde_json::json!([]));
+    assert_eq!(last["total"], 3);
+}
+
+#[tokio::test]
+async fn test_404_for_unknown_product_and_batch() {
+    let app = TestApp::new().await;
+    let (status, body) = app.get_json(&format!("/products/{}", random_sku(""))).await;
+    assert_eq!(status, 404);
+    assert_eq!(body["status"], "error");
+
+    let (status, _) = app
+        .get_json(&format!("/batches/{}", random_batch_ref("")))
+        .await;
+    assert_eq!(status, 404);
+}
+
+#[tokio::test]
+async fn test_partial_allocation_splits_line_and_deallocates_all_parts() {
+    let app = TestApp::new().await;
+    let sku = random_sku("SPLIT");
+    let batch1 = random_batch_ref("1");
+    let batch2 = random_batch_ref("2");
+    post_to_add_batch(&app, &batch1, &sku, 60, None).await;
+    post_to_add_batch(&app, &batch2, &sku, 60, Some("2011-01-01".to_string())).await;
+
+    let order_id = random_order_id("");
+
+    let whole = serde_json::json!({ "id": order_id, "sku": sku, "qty": 100 });
+    let (status, _) = app.post_json("/allocate", whole).await;
     assert_eq!(status, 400);
 
-    let body = response.into_body().collect().await.unwrap().to_bytes();
-    let body_str = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
-    let message = body_str.get("message").unwrap().as_str().unwrap();
-    assert_eq!(message, format!("Invalid sku {}", unknown_sku));
+    let partial = serde_json::json!({ "id": order_id, "sku": sku, "qty": 100, "partial": true });
+    let (status, body) = app.post_json("/allocate", partial).await;
+    assert_eq!(status, 201);
+
+    assert_eq!(
+        body["allocations"],
+        serde_json::json!([
+            { "batch_ref": batch1, "qty": 60, "warehouse": null },
+            { "batch_ref": batch2, "qty": 40, "warehouse": null },
+        ])
+    );
+
+    let (_, product) = app.get_json(&format!("/products/{}", sku)).await;
+    let allocated: i64 = product["batches"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|b| b["allocated_quantity"].as_i64().unwrap())
+        .sum();
+    assert_eq!(allocated, 100);
+
+    let dealloc = serde_json::json!({ "id": order_id, "sku": sku });
+    let (status, _) = app.post_json("/deallocate", dealloc).await;
+    assert_eq!(status, 200);
+
+    let (_, product) = app.get_json(&format!("/products/{}", sku)).await;
+    for batch in product["batches"].as_array().unwrap() {
+        assert_eq!(batch["allocated_quantity"], 0);
+        assert_eq!(batch["available_quantity"], 60);
+    }
+}
+
+async fn post_order_allocate(app: &TestApp, order_id: &str, lines: Value) -> (u16, Value) {
+    app.post_json(
+        &format!("/orders/{}/allocate", order_id),
+        serde_json::json!({ "lines": lines }),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_order_allocation_allocates_every_line() {
+    let app = TestApp::new().await;
+    let chair = random_sku("CHAIR");
+    let table = random_sku("TABLE");
+    let chair_batch = random_batch_ref("1");
+    let table_batch = random_batch_ref("2");
+    post_to_add_batch(&app, &chair_batch, &chair, 10, None).await;
+    post_to_add_batch(&app, &table_batch, &table, 10, None).await;
+
+    let order_id = random_order_id("");
+    let (status, body) = post_order_allocate(
+        &app,
+        &order_id,
+        serde_json::json!([
+            { "sku": chair, "qty": 4 },
+            { "sku": table, "qty": 1 },
+        ]),
+    )
+    .await;
+    assert_eq!(status, 201);
+    assert_eq!(body["order_id"], order_id);
+    assert_eq!(
+        body["lines"],
+        serde_json::json!([
+            { "sku": chair, "qty": 4, "batch_ref": chair_batch, "warehouse": null },
+            { "sku": table, "qty": 1, "batch_ref": table_batch, "warehouse": null },
+        ])
+    );
+
+    let (_, batch) = app.get_json(&format!("/batches/{}", chair_batch)).await;
+    assert_eq!(batch["allocated_quantity"], 4);
+    assert_eq!(batch["allocations"][0]["order_id"], order_id);
+    let (_, batch) = app.get_json(&format!("/batches/{}", table_batch)).await;
+    assert_eq!(batch["allocated_quantity"], 1);
+}
+
+#[tokio::test]
+async fn test_retried_allocation_returns_existing_batch() {
+    let app = TestApp::new().await;
+    let sku = random_sku("RETRY");
+    let first_batch = random_batch_ref("1");
+    let second_batch = random_batch_ref("2");
+    post_to_add_batch(&app, &first_batch, &sku, 10, None).await;
+    post_to_add_batch(
+        &app,
+        &second_batch,
+        &sku,
+        10,
+        Some("2011-01-02".to_string()),
+    )
+    .await;
+
+    // 第一次分配用完第一個批次，重試不可再佔用第二個批次
+    let order_id = random_order_id("");
+    assert_eq!(
+        post_to_allocate(&app, &order_id, &sku, 10).await,
+        first_batch
+    );
+    let (_, product) = app.get_json(&format!("/products/{}", sku)).await;
+    let version = product["version_number"].clone();
+
+    assert_eq!(
+        post_to_allocate(&app, &order_id, &sku, 10).await,
+        first_batch
+    );
+    // 重試不會再更新版本號
+    let (_, product) = app.get_json(&format!("/products/{}", sku)).await;
+    assert_eq!(product["version_number"], version);
+
+    // 數量不同的重試不會沿用或改變既有的分配
+    let (status, _) = app
+        .post_json(
+            "/allocate",
+            serde_json::json!({ "id": order_id, "sku": sku, "qty": 5 }),
+        )
+        .await;
+    assert_eq!(status, 400);
+
+    let (status, body) = post_order_allocate(
+        &app,
+        &order_id,
+        serde_json::json!([{ "sku": sku, "qty": 10 }]),
+    )
+    .await;
+    assert_eq!(status, 201);
+    assert_eq!(body["lines"][0]["batch_ref"], first_batch);
+
+    let (_, batch) = app.get_json(&format!("/batches/{}", first_batch)).await;
+    assert_eq!(batch["allocated_quantity"], 10);
+    let (_, batch) = app.get_json(&format!("/batches/{}", second_batch)).await;
+    assert_eq!(batch["allocated_quantity"], 0);
+}
+
+#[tokio::test]
+async fn test_order_allocation_rolls_back_when_any_line_is_out_of_stock() {
+    let app = TestApp::new().await;
+    let chair = random_sku("CHAIR");
+    let table = random_sku("TABLE");
+    let chair_batch = random_batch_ref("1");
+    let table_batch = random_batch_ref("2");
+    post_to_add_batch(&app, &chair_batch, &chair, 10, None).await;
+    post_to_add_batch(&app, &table_batch, &table, 1, None).await;
+
+    let (status, body) = post_order_allocate(
+        &app,
+        &random_order_id(""),
+        serde_json::json!([
+            { "sku": chair, "qty": 4 },
+            { "sku": table, "qty": 2 },
+        ]),
+    )
+    .await;
+    assert_eq!(status, 400);
+    assert_eq!(body["message"], format!("Out of stock for sku {}", table));
+
+    let (_, product) = app.get_json(&format!("/products/{}", chair)).await;
+    assert_eq!(product["batches"][0]["allocated_quantity"], 0);
+    assert_eq!(product["batches"][0]["available_quantity"], 10);
+}
+
+#[tokio::test]
+async fn test_allocation_prefers_nearest_warehouse_for_region() {
+    let app = TestApp::with_config(
+        AppConfig::builder()
+            .warehouse(WarehouseConfig {
+                regions: Some(HashMap::from([(
+                    "south".to_string(),
+                    vec!["KHH".to_string(), "TPE".to_string()],
+                )])),
+            })
+            .build(),
+    )
+    .await;
+    let sku = random_sku("DESK");
+    let north_batch = random_batch_ref("1");
+    let south_batch = random_batch_ref("2");
+    for (reference, warehouse) in [(&north_batch, "TPE"), (&south_batch, "KHH")] {
+        let (status, _) = app
+            .post_json(
+                "/add_batch",
+                serde_json::json!({
+                    "reference": reference,
+                    "sku": sku,
+                    "qty": 10,
+                    "eta": null,
+                    "warehouse": warehouse,
+                }),
+            )
+            .await;
+        assert_eq!(status, 201);
+    }
+
+    let (status, body) = app.post_json(
+        "/allocate",
+        serde_json::json!({ "id": random_order_id(""), "sku": sku, "qty": 5, "region": "south" }),
+    )
+    .await;
+    assert_eq!(status, 201);
+    assert_eq!(body["batch_ref"], south_batch.as_str());
+    assert_eq!(body["warehouse"], "KHH");
+
+    // 最近倉庫不足時改由下一個倉庫出貨
+    let (status, body) = app.post_json(
+        "/allocate",
+        serde_json::json!({ "id": random_order_id(""), "sku": sku, "qty": 8, "region": "south" }),
+    )
+    .await;
+    assert_eq!(status, 201);
+    assert_eq!(body["batch_ref"], north_batch.as_str());
+    assert_eq!(body["warehouse"], "TPE");
+
+    let (_, batch) = app.get_json(&format!("/batches/{}", south_batch)).await;
+    assert_eq!(batch["warehouse"], "KHH");
+}
+
+#[tokio::test]
+async fn test_warehouse_names_with_quotes_are_stored_verbatim() {
+    let warehouse = "O'Hare DC";
+    let app = TestApp::with_config(
+        AppConfig::builder()
+            .warehouse(WarehouseConfig {
+                regions: Some(HashMap::from([(
+                    "midwest".to_string(),
+                    vec![warehouse.to_string()],
+                )])),
+            })
+            .build(),
+    )
+    .await;
+    let sku = random_sku("QUOTE");
+    let batch_ref = random_batch_ref("");
+    let (status, _) = app
+        .post_json(
+            "/add_batch",
+            serde_json::json!({
+                "reference": batch_ref, "sku": sku, "qty": 10, "eta": null, "warehouse": warehouse,
+            }),
+        )
+        .await;
+    assert_eq!(status, 201);
+
+    let (status, body) = app.post_json(
+        "/allocate",
+        serde_json::json!({ "id": random_order_id(""), "sku": sku, "qty": 5, "region": "midwest" }),
+    )
+    .await;
+    assert_eq!(status, 201);
+    assert_eq!(body["warehouse"], warehouse);
+
+    let (_, batch) = app.get_json(&format!("/batches/{}", batch_ref)).await;
+    assert_eq!(batch["warehouse"], warehouse);
+}
+
+#[tokio::test]
+async fn test_production_profile_only_allows_listed_cors_origins() {
+    let app = TestApp::with_config(
+        AppConfig::builder()
+            .server(ServerConfig {
+                env: Some("production".to_string()),
+                cors_origins: Some(vec!["https://shop.example.com".to_string()]),
+                ..Default::default()
+            })
+            .build(),
+    )
+    .await;
+
+    let preflight = |origin: &str| {
+        axum::extract::Request::builder()
+            .method("OPTIONS")
+            .uri("/allocate")
+            .header("Origin", origin)
+            .header("Access-Control-Request-Method", "POST")
+            .body(axum::body::Body::empty())
+            .unwrap()
+    };
+
+    let res = app.send(preflight("https://shop.example.com")).await;
+    assert_eq!(
+        res.headers()["access-control-allow-origin"],
+        "https://shop.example.com"
+    );
+
+    let res = app.send(preflight("https://evil.example.com")).await;
+    assert!(!res.headers().contains_key("access-control-allow-origin"));
+
+    // 開發環境維持允許任何來源
+    let app = TestApp::new().await;
+    let res = app.send(preflight("https://evil.example.com")).await;
+    assert_eq!(res.headers()["access-control-allow-origin"], "*");
+}
+
+#[tokio::test]
+async fn test_reserve_rejects_out_of_range_ttl() {
+    let app = TestApp::new().await;
+    let sku = random_sku("TTL");
+    post_to_add_batch(&app, &random_batch_ref(""), &sku, 10, None).await;
+
+    for ttl_seconds in [u64::MAX, i64::MAX as u64 / 1000 + 1] {
+        let (status, body) = app
+            .post_json(
+                "/reserve",
+                serde_json::json!({
+                    "id": random_order_id(""), "sku": sku, "qty": 1, "ttl_seconds": ttl_seconds,
+                }),
+            )
+            .await;
+        assert_eq!(status, 400);
+        assert_eq!(body["message"], "ttl_seconds is out of range");
+    }
+
+    let (status, _) = app
+        .post_json(
+            "/reserve",
+            serde_json::json!({ "id": random_order_id(""), "sku": sku, "qty": 1, "ttl_seconds": 60 }),
+        )
+        .await;
+    assert_eq!(status, 201);
+}
+
+#[tokio::test]
+async fn test_order_ids_with_quotes_are_stored_verbatim() {
+    let app = TestApp::new().await;
+    let sku = random_sku("QUOTE");
+    let batch_ref = random_batch_ref("");
+    post_to_add_batch(&app, &batch_ref, &sku, 20, None).await;
+
+    // insert_sql 必須跳脫單引號，否則 SQL 會被截斷
+    let order_id = random_order_id("o'brien");
+    assert_eq!(post_to_allocate(&app, &order_id, &sku, 5).await, batch_ref);
+    assert_eq!(post_to_allocate(&app, &order_id, &sku, 5).await, batch_ref);
+    let (_, batch) = app.get_json(&format!("/batches/{}", batch_ref)).await;
+    assert_eq!(batch["allocated_quantity"], 5);
+
+    let reserved_id = random_order_id("d'arcy");
+    let (status, _) = app
+        .post_json(
+            "/reserve",
+            serde_json::json!({ "id": reserved_id, "sku": sku, "qty": 3 }),
+        )
+        .await;
+    assert_eq!(status, 201);
+    let (status, _) = app
+        .post_json(
+            &format!("/reservations/{}/confirm", reserved_id),
+            serde_json::json!({ "sku": sku }),
+        )
+        .await;
+    assert_eq!(status, 200);
+
+    let (_, batch) = app.get_json(&format!("/batches/{}", batch_ref)).await;
+    assert_eq!(batch["allocated_quantity"], 8);
+}
+
+#[tokio::test]
+async fn test_skus_with_quotes_find_their_existing_product() {
+    let app = TestApp::new().await;
+    let sku = random_sku("KID'S-CHAIR");
+
+    // 第二批要找到既有的商品，而不是查詢失敗或重建商品
+    post_to_add_batch(&app, &random_batch_ref("1"), &sku, 10, None).await;
+    post_to_add_batch(&app, &random_batch_ref("2"), &sku, 10, None).await;
+    messagebus::headle(
+        events::Event::BatchCreate(events::BatchCreate {
+            references: random_batch_ref("3"),
+            sku: sku.clone(),
+            qty: 10,
+            eta: None,
+            warehouse: None,
+        }),
+        &app.db,
+        &AppConfig::default(),
+    )
+    .await
+    .unwrap();
+
+    let (status, product) = app.get_json(&format!("/products/{}", sku)).await;
+    assert_eq!(status, 200);
+    assert_eq!(product["batches"].as_array().unwrap().len(), 3);
 }
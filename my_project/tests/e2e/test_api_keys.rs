@@ -0,0 +1,151 @@
+use architecture::repositories::update;
+use axum::body::Body;
+use axum::extract::Request;
+use serde_json::{Value, json};
+
+use crate::support::TestApp;
+
+/// 以 `Authorization: ApiKey <key>` 送出 JSON
+async fn post_with_key(app: &TestApp, uri: &str, key: &str, data: Value) -> u16 {
+    let request = Request::post(uri)
+        .header("content-type", "application/json")
+        .header("authorization", format!("ApiKey {}", key))
+        .body(Body::from(data.to_string()))
+        .unwrap();
+    app.send(request).await.status().as_u16()
+}
+
+async fn issue(app: &TestApp, scopes: Value) -> (String, String) {
+    let (status, body) = app
+        .post_json(
+            "/admin/api-keys",
+            json!({ "name": "order-service", "scopes": scopes }),
+        )
+        .await;
+    assert_eq!(status, 201);
+    assert_eq!(body["scopes"], scopes);
+    (
+        body["id"].as_str().unwrap().to_string(),
+        body["key"].as_str().unwrap().to_string(),
+    )
+}
+
+#[tokio::test]
+async fn test_api_key_allocates_and_records_last_used() {
+    let app = TestApp::new().await;
+    let (status, _) = app
+        .post_json(
+            "/add_batch",
+            json!({ "reference": "key-batch", "sku": "KEY-LAMP", "qty": 10 }),
+        )
+        .await;
+    assert_eq!(status, 201);
+
+    let (id, key) = issue(&app, json!(["sales"])).await;
+    let order = json!({ "id": "key-order", "sku": "KEY-LAMP", "qty": 1 });
+    assert_eq!(post_with_key(&app, "/allocate", &key, order).await, 201);
+
+    // 只保存雜湊，列表不會出現金鑰或雜湊
+    let (status, body) = app.get_json("/admin/api-keys").await;
+    assert_eq!(status, 200);
+    let listed = &body.as_array().unwrap()[0];
+    assert_eq!(listed["id"], id.as_str());
+    assert_eq!(listed["scopes"], "sales");
+    assert!(listed["last_used_at"].is_string());
+    assert!(listed["revoked_at"].is_null());
+    assert!(listed.get("key_hash").is_none());
+    assert!(!body.to_string().contains(key.split_once('.').unwrap().1));
+
+    // 範圍外的 route 回 403
+    let batch = json!({ "reference": "key-batch-2", "sku": "KEY-LAMP", "qty": 10 });
+    assert_eq!(post_with_key(&app, "/add_batch", &key, batch).await, 403);
+}
+
+#[tokio::test]
+async fn test_revoked_or_wrong_api_key_is_rejected() {
+    let app = TestApp::new().await;
+    let (id, key) = issue(&app, json!(["inventory"])).await;
+    let batch = |reference: &str| json!({ "reference": reference, "sku": "KEY-DESK", "qty": 10 });
+
+    assert_eq!(
+        post_with_key(&app, "/add_batch", &key, batch("key-b1")).await,
+        201
+    );
+    let forged = format!("{}.{}", id, "x".repeat(43));
+    assert_eq!(
+        post_with_key(&app, "/add_batch", &forged, batch("key-b2")).await,
+        401
+    );
+    assert_eq!(
+        post_with_key(&app, "/add_batch", "not-a-key", batch("key-b2")).await,
+        401
+    );
+
+    let (status, _) = app
+        .request("DELETE", &format!("/admin/api-keys/{}", id), None)
+        .await;
+    assert_eq!(status, 204);
+    assert_eq!(
+        post_with_key(&app, "/add_batch", &key, batch("key-b3")).await,
+        401
+    );
+
+    let (status, _) = app
+        .request("DELETE", &format!("/admin/api-keys/{}", id), None)
+        .await;
+    assert_eq!(status, 404);
+}
+
+#[tokio::test]
+async fn test_issue_api_key_requires_scopes() {
+    let app = TestApp::new().await;
+    let (status, body) = app
+        .post_json(
+            "/admin/api-keys",
+            json!({ "name": "order-service", "scopes": [] }),
+        )
+        .await;
+    assert_eq!(status, 400);
+    assert_eq!(body["message"], "API key requires at least one scope");
+}
+
+#[tokio::test]
+async fn test_api_key_name_with_quote_is_stored_verbatim() {
+    let app = TestApp::new().await;
+    let name = "o'brien's importer";
+    let (status, body) = app
+        .post_json(
+            "/admin/api-keys",
+            json!({ "name": name, "scopes": ["inventory"] }),
+        )
+        .await;
+    assert_eq!(status, 201);
+    let key = body["key"].as_str().unwrap().to_string();
+
+    let (_, body) = app.get_json("/admin/api-keys").await;
+    assert_eq!(body[0]["name"], name);
+
+    let batch = json!({ "reference": "key-quote", "sku": "KEY-QUOTE", "qty": 10 });
+    assert_eq!(post_with_key(&app, "/add_batch", &key, batch).await, 201);
+}
+
+#[tokio::test]
+async fn test_failed_last_used_update_does_not_reject_valid_key() {
+    let app = TestApp::new().await;
+    let (_, key) = issue(&app, json!(["inventory"])).await;
+
+    // 模擬寫入失敗（例如 SQLite 忙碌）
+    update(
+        &app.db,
+        "CREATE TRIGGER api_key_busy BEFORE UPDATE OF last_used_at ON api_key \
+         BEGIN SELECT RAISE(ABORT, 'database is locked'); END",
+    )
+    .await
+    .unwrap();
+
+    let batch = json!({ "reference": "key-busy", "sku": "KEY-BUSY", "qty": 10 });
+    assert_eq!(post_with_key(&app, "/add_batch", &key, batch).await, 201);
+
+    let (_, body) = app.get_json("/admin/api-keys").await;
+    assert!(body[0]["last_used_at"].is_null());
+}
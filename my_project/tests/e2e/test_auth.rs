@@ -0,0 +1,208 @@
+use architecture::auth::{self, Claims, TokenKind};
+use architecture::configures::{AppConfig, AuthConfig, Role, UserConfig};
+use axum::body::Body;
+use axum::extract::Request;
+use chrono::Utc;
+use jsonwebtoken::{EncodingKey, Header};
+use serde_json::{Value, json};
+
+use crate::support::{LogCapture, TestApp};
+
+/// 以指定的 bearer token（或不帶）送出 JSON
+async fn post_as(app: &TestApp, uri: &str, token: Option<&str>, data: Value) -> u16 {
+    let mut request = Request::post(uri).header("content-type", "application/json");
+    if let Some(token) = token {
+        request = request.header("authorization", format!("Bearer {}", token));
+    }
+    let response = app
+        .send(request.body(Body::from(data.to_string())).unwrap())
+        .await;
+    response.status().as_u16()
+}
+
+fn batch(reference: &str) -> Value {
+    json!({ "reference": reference, "sku": "AUTH-LAMP", "qty": 10 })
+}
+
+#[tokio::test]
+async fn test_protected_routes_require_valid_bearer_token() {
+    let app = TestApp::new().await;
+
+    assert_eq!(post_as(&app, "/add_batch", None, batch("b1")).await, 401);
+    assert_eq!(
+        post_as(&app, "/add_batch", Some("not-a-jwt"), batch("b1")).await,
+        401
+    );
+
+    let expired = Claims {
+        sub: "tester".to_string(),
+        roles: vec![Role::Inventory],
+        kind: TokenKind::Access,
+        iat: Utc::now().timestamp() - 7200,
+        exp: Utc::now().timestamp() - 3600,
+    };
+    let expired = jsonwebtoken::encode(
+        &Header::default(),
+        &expired,
+        &EncodingKey::from_secret(app.secret().jwt_secret.as_deref().unwrap().as_bytes()),
+    )
+    .unwrap();
+    assert_eq!(
+        post_as(&app, "/add_batch", Some(&expired), batch("b1")).await,
+        401
+    );
+
+    // 查詢與探測不需要登入
+    let (status, _) = app.get_json("/products").await;
+    assert_eq!(status, 200);
+    let response = app
+        .send(Request::get("/healthz").body(Body::empty()).unwrap())
+        .await;
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+async fn test_roles_gate_inventory_and_sales_routes() {
+    let app = TestApp::new().await;
+    let inventory = app.token(&[Role::Inventory]);
+    let sales = app.token(&[Role::Sales]);
+
+    assert_eq!(
+        post_as(&app, "/add_batch", Some(&sales), batch("b1")).await,
+        403
+    );
+    assert_eq!(
+        post_as(&app, "/add_batch", Some(&inventory), batch("b1")).await,
+        201
+    );
+
+    let line = json!({ "id": "auth-order", "sku": "AUTH-LAMP", "qty": 1 });
+    assert_eq!(
+        post_as(&app, "/allocate", Some(&inventory), line.clone()).await,
+        403
+    );
+    assert_eq!(post_as(&app, "/allocate", Some(&sales), line).await, 201);
+
+    let response = app
+        .send(
+            Request::get("/admin/log-level")
+                .header("authorization", format!("Bearer {}", sales))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+    assert_eq!(response.status(), 403);
+}
+
+#[tokio::test]
+async fn test_token_and_refresh_flow() {
+    let config = AppConfig::builder()
+        .auth(AuthConfig {
+            users: vec![UserConfig {
+                username: "clerk".to_string(),
+                password_hash: auth::hash_password("s3cret"),
+                roles: vec![Role::Inventory],
+            }],
+            ..Default::default()
+        })
+        .build();
+    let app = TestApp::with_config(config).await;
+
+    let (status, _) = app
+        .post_json(
+            "/auth/token",
+            json!({ "username": "clerk", "password": "wrong" }),
+        )
+        .await;
+    assert_eq!(status, 401);
+    let (status, _) = app
+        .post_json(
+            "/auth/token",
+            json!({ "username": "nobody", "password": "s3cret" }),
+        )
+        .await;
+    assert_eq!(status, 401);
+
+    let (status, body) = app
+        .post_json(
+            "/auth/token",
+            json!({ "username": "clerk", "password": "s3cret" }),
+        )
+        .await;
+    assert_eq!(status, 200);
+    assert_eq!(body["token_type"], "Bearer");
+    assert_eq!(body["expires_in"], 900);
+    let access = body["access_token"].as_str().unwrap().to_string();
+    let refresh = body["refresh_token"].as_str().unwrap().to_string();
+
+    assert_eq!(
+        post_as(&app, "/add_batch", Some(&access), batch("b1")).await,
+        201
+    );
+    // refresh token 不能拿來呼叫 API，access token 也不能拿來換發
+    assert_eq!(
+        post_as(&app, "/add_batch", Some(&refresh), batch("b2")).await,
+        401
+    );
+    let (status, _) = app
+        .post_json("/auth/refresh", json!({ "refresh_token": access }))
+        .await;
+    assert_eq!(status, 401);
+
+    let (status, body) = app
+        .post_json("/auth/refresh", json!({ "refresh_token": refresh }))
+        .await;
+    assert_eq!(status, 200);
+    let renewed = body["access_token"].as_str().unwrap();
+    assert_eq!(
+        post_as(&app, "/add_batch", Some(renewed), batch("b3")).await,
+        201
+    );
+}
+
+#[tokio::test]
+async fn test_out_of_range_token_lifetime_is_an_error() {
+    // builder 不經過設定檢查，超出範圍的期限要回傳錯誤而不是 panic
+    let config = AppConfig::builder()
+        .auth(AuthConfig {
+            refresh_ttl_seconds: Some(u64::MAX),
+            users: vec![UserConfig {
+                username: "clerk".to_string(),
+                password_hash: auth::hash_password("s3cret"),
+                roles: vec![Role::Inventory],
+            }],
+            ..Default::default()
+        })
+        .build();
+    let app = TestApp::with_config(config).await;
+
+    let (status, _) = app
+        .post_json(
+            "/auth/token",
+            json!({ "username": "clerk", "password": "s3cret" }),
+        )
+        .await;
+    assert_eq!(status, 500);
+}
+
+#[tokio::test]
+async fn test_unknown_user_still_verifies_a_password_hash() {
+    let app = TestApp::new().await;
+    let capture = LogCapture::default();
+    let _default = capture.install();
+
+    // 不存在的帳號也會跑一次 argon2，固定的雜湊必須是合法的 PHC 字串
+    let (status, _) = app
+        .post_json(
+            "/auth/token",
+            json!({ "username": "nobody", "password": "s3cret" }),
+        )
+        .await;
+    assert_eq!(status, 401);
+    assert!(
+        capture
+            .lines()
+            .iter()
+            .all(|line| !line.to_string().contains("Invalid password hash"))
+    );
+}
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::support::TestApp;
+
+const SKU: &str = "CONTENDED-LAMP";
+
+/// 同時送出 requests 個 /allocate，每個要 qty 件，回傳每個請求的 (狀態碼, 回應)
+async fn allocate_concurrently(app: &Arc<TestApp>, requests: usize, qty: u32) -> Vec<(u16, Value)> {
+    let barrier = Arc::new(tokio::sync::Barrier::new(requests));
+
+    let handles = (0..requests)
+        .map(|i| {
+            let app = app.clone();
+            let barrier = barrier.clone();
+            tokio::spawn(async move {
+                barrier.wait().await;
+                app.post_json(
+                    "/allocate",
+                    serde_json::json!({ "id": format!("order-{}", i), "sku": SKU, "qty": qty }),
+                )
+                .await
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut results = Vec::with_capacity(requests);
+    for handle in handles {
+        results.push(handle.await.unwrap());
+    }
+    results
+}
+
+/// 不變量：已分配量不超過進貨量，成功的請求數量加總等於已分配量，
+/// 其他請求只能是版本衝突或缺貨
+async fn assert_allocation_invariants(app: &TestApp, results: &[(u16, Value)], qty: u32) {
+    let (status, product) = app.get_json(&format!("/products/{}", SKU)).await;
+    assert_eq!(status, 200);
+
+    let mut allocated = 0;
+    let mut purchased = 0;
+    for batch in product["batches"].as_array().unwrap() {
+        let batch_allocated = batch["allocated_quantity"].as_i64().unwrap();
+        let batch_purchased = batch["purchased_quantity"].as_i64().unwrap();
+        assert!(
+            batch_allocated <= batch_purchased,
+            "batch {} over-allocated: {} > {}",
+            batch["reference"],
+            batch_allocated,
+            batch_purchased
+        );
+        allocated += batch_allocated;
+        purchased += batch_purchased;
+    }
+    assert!(allocated <= purchased);
+
+    let mut succeeded = 0;
+    for (status, body) in results {
+        match status {
+            201 => {
+                assert!(body["batch_ref"].is_string(), "missing batch_ref: {}", body);
+                succeeded += 1;
+            }
+            409 => assert!(
+                body["message"].as_str().is_some_and(|m| !m.is_empty()),
+                "conflict without message: {}",
+                body
+            ),
+            400 => assert_eq!(body["message"], format!("Out of stock for sku {}", SKU)),
+            _ => panic!("unexpected response {}: {}", status, body),
+        }
+    }
+    assert_eq!(allocated, succeeded * qty as i64);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_allocations_never_exceed_stock() {
+    let app = Arc::new(TestApp::new().await);
+
+    let (status, _) = app
+        .post_json(
+            "/add_batch",
+            serde_json::json!({ "reference": "batch1", "sku": SKU, "qty": 50, "eta": null }),
+        )
+        .await;
+    assert_eq!(status, 201);
+
+    // 需求量（20 x 10）遠大於庫存
+    let results = allocate_concurrently(&app, 20, 10).await;
+    assert_allocation_invariants(&app, &results, 10).await;
+
+    assert!(results.iter().any(|(status, _)| *status == 201));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_allocations_across_batches() {
+    let app = Arc::new(TestApp::new().await);
+
+    for (reference, qty, eta) in [
+        ("stock", 30, Value::Null),
+        ("shipment", 30, Value::String("2011-01-01".to_string())),
+    ] {
+        let (status, _) = app
+            .post_json(
+                "/add_batch",
+                serde_json::json!({ "reference": reference, "sku": SKU, "qty": qty, "eta": eta }),
+            )
+            .await;
+        assert_eq!(status, 201);
+    }
+
+    let results = allocate_concurrently(&app, 16, 7).await;
+    assert_allocation_invariants(&app, &results, 7).await;
+}
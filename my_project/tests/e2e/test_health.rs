@@ -0,0 +1,50 @@
+use architecture::repositories::DbPool;
+
+use crate::support::TestApp;
+
+#[tokio::test]
+async fn test_healthz_reports_alive() {
+    let app = TestApp::new().await;
+
+    let (status, body) = app.get_json("/healthz").await;
+    assert_eq!(status, 200);
+    assert_eq!(body["status"], "ok");
+}
+
+#[tokio::test]
+async fn test_readyz_checks_database_migrations_and_workers() {
+    let app = TestApp::new().await;
+
+    let (status, body) = app.get_json("/readyz").await;
+    assert_eq!(status, 200);
+    assert_eq!(body["status"], "ready");
+    assert_eq!(body["checks"]["database"], "ok");
+    assert_eq!(body["checks"]["migrations"], "ok");
+    assert_eq!(body["checks"]["workers"], "ok");
+
+    // 背景工作結束後不再 ready
+    let sweeper = tokio::spawn(async {});
+    app.workers.register("reservation_sweeper", &sweeper);
+    sweeper.await.unwrap();
+
+    let (status, body) = app.get_json("/readyz").await;
+    assert_eq!(status, 503);
+    assert_eq!(body["status"], "not_ready");
+    assert_eq!(body["checks"]["workers"], "stopped: reservation_sweeper");
+
+    app.db.close().await;
+    let (status, body) = app.get_json("/readyz").await;
+    assert_eq!(status, 503);
+    assert_eq!(body["checks"]["migrations"], "database unreachable");
+}
+
+#[tokio::test]
+async fn test_version_reports_build_and_migration() {
+    let app = TestApp::new().await;
+
+    let (status, body) = app.get_json("/version").await;
+    assert_eq!(status, 200);
+    assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+    assert!(body["git_hash"].is_string());
+    assert_eq!(body["migration"].as_i64(), DbPool::expected_migration());
+}
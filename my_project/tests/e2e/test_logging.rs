@@ -0,0 +1,54 @@
+use architecture::configures::Role;
+use axum::body::Body;
+use axum::extract::Request;
+use serde_json::json;
+
+use crate::support::{LogCapture, TestApp};
+
+#[tokio::test]
+async fn test_json_logs_carry_request_span_fields() {
+    let app = TestApp::new().await;
+    let (status, _) = app
+        .post_json(
+            "/add_batch",
+            json!({ "reference": "log-batch", "sku": "LOG-LAMP", "qty": 10 }),
+        )
+        .await;
+    assert_eq!(status, 201);
+
+    let capture = LogCapture::default();
+    let _default = capture.install();
+
+    let response = app
+        .send(
+            Request::post("/allocate")
+                .header("content-type", "application/json")
+                .header("x-request-id", "req-123")
+                .header(
+                    "authorization",
+                    format!("Bearer {}", app.token(&[Role::Sales])),
+                )
+                .body(Body::from(
+                    json!({ "id": "log-order", "sku": "LOG-LAMP", "qty": 1 }).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await;
+    assert_eq!(response.status(), 201);
+
+    let lines = capture.lines();
+    let finished = lines
+        .iter()
+        .find(|line| line["message"] == "finished processing request")
+        .expect("the trace layer logs the response");
+    assert_eq!(finished["level"], "INFO");
+    assert!(finished["timestamp"].is_string());
+
+    let span = &finished["span"];
+    assert_eq!(span["name"], "sitemap");
+    assert_eq!(span["method"], "POST");
+    assert_eq!(span["uri"], "/allocate");
+    assert_eq!(span["request_id"], "req-123");
+    assert_eq!(span["sku"], "LOG-LAMP");
+    assert_eq!(span["order_id"], "log-order");
+}
@@ -0,0 +1,102 @@
+use architecture::configures::AppConfig;
+use architecture::{events, messagebus};
+use axum::body::Body;
+use axum::extract::Request;
+use http_body_util::BodyExt;
+use serde_json::json;
+
+use crate::support::TestApp;
+
+async fn scrape(app: &TestApp) -> String {
+    let response = app
+        .send(Request::get("/metrics").body(Body::empty()).unwrap())
+        .await;
+    assert_eq!(response.status(), 200);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+/// 其他測試會並行累加同一組全域指標，只比較增量
+fn value(metrics: &str, series: &str) -> f64 {
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(series)?.trim().parse().ok())
+        .unwrap_or(0.0)
+}
+
+#[tokio::test]
+async fn test_metrics_count_requests_and_allocation_outcomes() {
+    let app = TestApp::new().await;
+    let allocated = r#"allocations_total{outcome="allocated"}"#;
+    let out_of_stock = r#"allocations_total{outcome="out_of_stock"}"#;
+    let before = scrape(&app).await;
+
+    let (status, _) = app
+        .post_json(
+            "/add_batch",
+            json!({ "reference": "metrics-batch", "sku": "METRICS-LAMP", "qty": 10 }),
+        )
+        .await;
+    assert_eq!(status, 201);
+    let (status, _) = app
+        .post_json(
+            "/allocate",
+            json!({ "id": "metrics-order1", "sku": "METRICS-LAMP", "qty": 10 }),
+        )
+        .await;
+    assert_eq!(status, 201);
+    let (status, _) = app
+        .post_json(
+            "/allocate",
+            json!({ "id": "metrics-order2", "sku": "METRICS-LAMP", "qty": 1 }),
+        )
+        .await;
+    assert_eq!(status, 400);
+    let (status, _) = app.get_json("/products/METRICS-LAMP").await;
+    assert_eq!(status, 200);
+
+    let after = scrape(&app).await;
+    assert!(value(&after, allocated) >= value(&before, allocated) + 1.0);
+    assert!(value(&after, out_of_stock) >= value(&before, out_of_stock) + 1.0);
+    assert!(after.contains(r#"http_requests_total{method="POST",route="/allocate",status="201"}"#));
+    // 路徑參數以 route 樣板記錄
+    assert!(after.contains(r#"route="/products/{sku}""#));
+    assert!(!after.contains("METRICS-LAMP"));
+    assert!(!after.contains(r#"route="/metrics""#));
+    assert_eq!(value(&after, "db_pool_max_connections"), 8.0);
+}
+
+#[tokio::test]
+async fn test_metrics_record_message_bus_handlers() {
+    let app = TestApp::new().await;
+    let config = AppConfig::default();
+    let (status, _) = app
+        .post_json(
+            "/add_batch",
+            json!({ "reference": "metrics-bus-batch", "sku": "METRICS-BUS-LAMP", "qty": 1 }),
+        )
+        .await;
+    assert_eq!(status, 201);
+
+    let result = messagebus::headle(
+        events::Event::AllocateRequired(events::AllocateRequired {
+            order_id: "metrics-bus-order".to_string(),
+            sku: "METRICS-BUS-LAMP".to_string(),
+            qty: 5,
+        }),
+        &app.db,
+        &config,
+    )
+    .await;
+    assert_eq!(result.unwrap_err(), "Out of stock for sku METRICS-BUS-LAMP");
+
+    let metrics = scrape(&app).await;
+    assert!(
+        value(
+            &metrics,
+            r#"messagebus_handler_duration_seconds_count{event="allocate_required"}"#
+        ) >= 1.0
+    );
+    assert!(metrics.contains("messagebus_queue_depth"));
+    assert!(value(&metrics, r#"allocations_total{outcome="out_of_stock"}"#) >= 1.0);
+}
@@ -0,0 +1,150 @@
+use architecture::configures::AppConfig;
+use architecture::{events, messagebus, sitemaps};
+use axum::body::Body;
+use axum::extract::Request;
+use axum::{Router, middleware, routing::post};
+use http_body_util::BodyExt;
+use serde_json::json;
+use tower::ServiceExt;
+
+use crate::support::{LogCapture, TestApp};
+
+#[tokio::test]
+async fn test_request_id_is_generated_or_echoed() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .send(Request::get("/products").body(Body::empty()).unwrap())
+        .await;
+    let generated = response.headers()["x-request-id"].to_str().unwrap();
+    assert!(!generated.is_empty());
+
+    let response = app
+        .send(
+            Request::get("/products")
+                .header("x-request-id", "upstream-42")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+    assert_eq!(response.headers()["x-request-id"], "upstream-42");
+
+    // 過長或含有其他字元的 id 不會進到 log 與事件，改用新產生的 id
+    for invalid in [
+        "x".repeat(129),
+        "id with spaces".to_string(),
+        "a\"b".to_string(),
+    ] {
+        let response = app
+            .send(
+                Request::get("/products")
+                    .header("x-request-id", invalid.as_str())
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+        let replaced = response.headers()["x-request-id"].to_str().unwrap();
+        assert_ne!(replaced, invalid);
+        assert!(sitemaps::is_valid_request_id(replaced));
+    }
+}
+
+#[tokio::test]
+async fn test_sensitive_headers_are_redacted_from_span() {
+    let app = TestApp::new().await;
+    let capture = LogCapture::default();
+    let _default = capture.install();
+
+    app.send(
+        Request::get("/products")
+            .header("authorization", "Bearer secret-token")
+            .header("x-api-key", "secret-key")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+
+    let lines = capture.lines();
+    let finished = lines
+        .iter()
+        .find(|line| line["message"] == "finished processing request")
+        .unwrap();
+    let headers = finished["span"]["headers"].as_str().unwrap();
+    assert!(headers.contains("Sensitive"));
+    assert!(finished["span"]["request_id"].is_string());
+    for line in &lines {
+        let line = line.to_string();
+        assert!(!line.contains("secret-token"));
+        assert!(!line.contains("secret-key"));
+    }
+}
+
+#[tokio::test]
+async fn test_message_bus_logs_carry_request_id() {
+    let app = TestApp::new().await;
+    let (status, _) = app
+        .post_json(
+            "/add_batch",
+            json!({ "reference": "rid-batch", "sku": "RID-LAMP", "qty": 10 }),
+        )
+        .await;
+    assert_eq!(status, 201);
+
+    let capture = LogCapture::default();
+    let _default = capture.install();
+
+    let event = events::Event::AllocateRequired(events::AllocateRequired {
+        order_id: "rid-order".to_string(),
+        sku: "RID-LAMP".to_string(),
+        qty: 1,
+    });
+    messagebus::headle(
+        events::Envelope::new(event, "req-bus-7"),
+        &app.db,
+        &AppConfig::default(),
+    )
+    .await
+    .unwrap();
+
+    let lines = capture.lines();
+    let handling = lines
+        .iter()
+        .find(|line| line["message"] == "Handling event allocate_required")
+        .expect("the bus logs each event it handles");
+    assert_eq!(handling["span"]["name"], "messagebus");
+    assert_eq!(handling["span"]["request_id"], "req-bus-7");
+}
+
+#[tokio::test]
+async fn test_events_raised_during_a_request_carry_its_id() {
+    let event = || {
+        events::Event::OutOfStock(events::OutOfStock {
+            sku: "RID-DESK".to_string(),
+        })
+    };
+    let route = Router::new()
+        .route(
+            "/emit",
+            post(move || async move { events::Envelope::from(event()).request_id }),
+        )
+        .layer(middleware::from_fn(sitemaps::scope_request_id));
+
+    let request = Request::post("/emit")
+        .header("x-request-id", "req-emit-1")
+        .body(Body::empty())
+        .unwrap();
+    let res = route.clone().oneshot(request).await.unwrap();
+    let body = res.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&body[..], b"req-emit-1");
+
+    let request = Request::post("/emit")
+        .header("x-request-id", "x".repeat(129))
+        .body(Body::empty())
+        .unwrap();
+    let res = route.clone().oneshot(request).await.unwrap();
+    let body = res.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(body.len(), 20);
+
+    // request 之外（背景工作）各自產生新的 id
+    assert_ne!(events::Envelope::from(event()).request_id, "req-emit-1");
+}
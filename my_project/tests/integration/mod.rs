@@ -1,3 +1,8 @@
+pub mod test_arrivals;
+pub mod test_database;
 pub mod test_orm;
+pub mod test_postgres;
+pub mod test_reload;
 pub mod test_repository;
+pub mod test_reservations;
 pub mod test_uow;
@@ -0,0 +1,86 @@
+use architecture::configures::AppConfig;
+use architecture::repositories::DbPool;
+use architecture::{arrivals, services};
+use chrono::{DateTime, Duration, Utc};
+
+use crate::support::memory_db;
+
+async fn add_batch(db: &DbPool, reference: &str, qty: u32, eta: Option<DateTime<Utc>>) {
+    let mut tx = db.begin().await.unwrap();
+    services::add_batch(reference, "ARRIVING-SOFA", qty, eta, None, &mut tx)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+}
+
+async fn allocate(db: &DbPool, order_id: &str, qty: u32) -> String {
+    let mut tx = db.begin().await.unwrap();
+    let allocated = services::allocate_order(
+        order_id,
+        &[("ARRIVING-SOFA".to_string(), qty)],
+        None,
+        &AppConfig::default(),
+        &mut tx,
+    )
+    .await
+    .unwrap();
+    tx.commit().await.unwrap();
+    allocated[0].1.clone()
+}
+
+async fn batch(db: &DbPool, reference: &str) -> architecture::chapter1::Batch {
+    let mut tx = db.begin().await.unwrap();
+    services::load_batches(&mut tx, "ARRIVING-SOFA")
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|b| b.reference == reference)
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_checker_marks_due_shipments_as_arrived() {
+    let db = memory_db().await;
+    add_batch(&db, "due", 20, Some(Utc::now() - Duration::hours(1))).await;
+    add_batch(&db, "later", 20, Some(Utc::now() + Duration::days(10))).await;
+    add_batch(&db, "stock", 20, None).await;
+
+    let arrived = arrivals::check(&db).await.unwrap();
+    assert_eq!(arrived.len(), 1);
+    assert_eq!(arrived[0].reference, "due");
+    assert_eq!(arrived[0].sku, "ARRIVING-SOFA");
+    assert_eq!(arrived[0].qty, 20);
+
+    assert_eq!(batch(&db, "due").await.eta, None);
+    assert!(batch(&db, "later").await.eta.is_some());
+
+    // 已到貨的批次不會重複處理
+    assert!(arrivals::check(&db).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_reallocates_lines_from_later_shipments_to_arrived_stock() {
+    let db = memory_db().await;
+    add_batch(&db, "later", 50, Some(Utc::now() + Duration::days(10))).await;
+    assert_eq!(allocate(&db, "order1", 10).await, "later");
+    assert_eq!(allocate(&db, "order2", 30).await, "later");
+
+    add_batch(&db, "due", 20, Some(Utc::now() - Duration::hours(1))).await;
+    arrivals::check(&db).await.unwrap();
+
+    let mut tx = db.begin().await.unwrap();
+    let moved = services::reallocate_to_arrived("due", &mut tx)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+
+    // order2 超過到貨批次的可用量，留在原批次
+    assert_eq!(moved, vec![("order1".to_string(), "later".to_string())]);
+
+    let due = batch(&db, "due").await;
+    assert_eq!(due.allocated_quantity_for("order1"), 10);
+    assert_eq!(due.available_quantity(), 10);
+    let later = batch(&db, "later").await;
+    assert_eq!(later.allocated_quantity_for("order1"), 0);
+    assert_eq!(later.allocated_quantity_for("order2"), 30);
+}
@@ -0,0 +1,41 @@
+use std::collections::BTreeMap;
+
+use architecture::configures::{DatabaseConfig, JournalMode};
+use architecture::repositories::DbPool;
+
+#[tokio::test]
+async fn test_sqlite_connection_applies_tuning() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = DatabaseConfig {
+        database: Some(dir.path().join("tuned").display().to_string()),
+        max_connections: Some(2),
+        journal_mode: Some(JournalMode::Truncate),
+        foreign_keys: Some(false),
+        pragmas: Some(BTreeMap::from([(
+            "cache_size".to_string(),
+            "-4096".to_string(),
+        )])),
+        ..Default::default()
+    };
+
+    let DbPool::Sqlite(pool) = config.get_connection().await.unwrap() else {
+        panic!("expected a SQLite pool");
+    };
+    let pragma = |name: &'static str| {
+        let pool = pool.clone();
+        async move {
+            sqlx::query_scalar::<_, String>(&format!(
+                "SELECT CAST({} AS TEXT) FROM pragma_{}",
+                name, name
+            ))
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+        }
+    };
+
+    assert_eq!(pool.options().get_max_connections(), 2);
+    assert_eq!(pragma("journal_mode").await, "truncate");
+    assert_eq!(pragma("foreign_keys").await, "0");
+    assert_eq!(pragma("cache_size").await, "-4096");
+}
@@ -1,3 +1,4 @@
+use architecture::repositories::DbPool;
 use architecture::repositories::create;
 use architecture::repositories::read;
 use architecture::repositories::read_one;
@@ -8,20 +9,20 @@ use architecture::{
         order_lines::{self, OrderLine},
     },
 };
-use sqlx::Executor;
-use sqlx::SqlitePool;
 use sqlx::sqlite::SqlitePoolOptions;
 
-async fn in_memory_db() -> SqlitePool {
+async fn in_memory_db() -> DbPool {
     SqlitePoolOptions::new()
         .max_connections(1)
         .connect("sqlite::memory:")
         .await
         .unwrap()
+        .into()
 }
 
-async fn start_mappers(db: &SqlitePool) {
-    db.execute(
+async fn start_mappers(db: &DbPool) {
+    create(
+        db,
         "CREATE TABLE test_table (
                 id INTEGER PRIMARY KEY,
                 name TEXT,
@@ -32,7 +33,8 @@ async fn start_mappers(db: &SqlitePool) {
     .await
     .unwrap();
 
-    db.execute(
+    create(
+        db,
         r"
         CREATE TABLE batch (
             id TEXT PRIMARY KEY,
@@ -40,6 +42,8 @@ async fn start_mappers(db: &SqlitePool) {
             sku TEXT,
             qty INTEGER,
             eta TEXT,
+            warehouse TEXT,
+            arrived_at TEXT,
             created_at TEXT,
             updated_at TEXT
         )",
@@ -47,10 +51,12 @@ async fn start_mappers(db: &SqlitePool) {
     .await
     .unwrap();
 
-    db.execute(
+    create(
+        db,
         r"
         CREATE TABLE order_line (
             id TEXT PRIMARY KEY,
+            order_id TEXT,
             sku TEXT,
             qty INTEGER,
             created_at TEXT,
@@ -60,12 +66,14 @@ async fn start_mappers(db: &SqlitePool) {
     .await
     .unwrap();
 
-    db.execute(
+    create(
+        db,
         r"
         CREATE TABLE allocation (
             id TEXT PRIMARY KEY,
             order_line_id TEXT,
             batch_id TEXT,
+            qty INTEGER,
             created_at TEXT,
             updated_at TEXT
         )",
@@ -79,7 +87,8 @@ async fn test_orderline_mapper_can_load_lines() {
     let db = in_memory_db().await;
     start_mappers(&db).await;
 
-    db.execute(
+    create(
+        &db,
         r"
         INSERT INTO order_line (id, sku, qty, created_at, updated_at)
         VALUES ('order1', 'RED-CHAIR', 12, '2025-12-08T00:00:00', '2025-12-08T00:00:00'),
@@ -108,10 +117,9 @@ async fn test_orderline_mapper_can_load_lines() {
         },
     ];
 
-    let line_ents: Vec<OrderLine> =
-        read::<&SqlitePool, OrderLine>(&db, &OrderLine::select_sql(None))
-            .await
-            .unwrap();
+    let line_ents: Vec<OrderLine> = read::<&DbPool, OrderLine>(&db, &OrderLine::select_sql(None))
+        .await
+        .unwrap();
     let lines: Vec<chapter1::OrderLine> = line_ents
         .into_iter()
         .map(|line_ent| line_ent.build())
@@ -128,6 +136,7 @@ async fn test_orderline_mapper_can_save_line() {
 
     let new_line = OrderLine {
         id: "order1".to_string(),
+        order_id: None,
         sku: "DECORATIVE-WIDGET".to_string(),
         qty: 12,
         created_at: chrono::NaiveDate::from_ymd_opt(2025, 12, 8)
@@ -144,7 +153,7 @@ async fn test_orderline_mapper_can_save_line() {
 
     create(&db, &new_line.insert_sql()).await.unwrap();
 
-    let fetched_line = read_one::<&SqlitePool, OrderLine>(
+    let fetched_line = read_one::<&DbPool, OrderLine>(
         &db,
         &OrderLine::select_sql(Some(&OrderLine::where_eq("id", "'order1'"))),
     )
@@ -163,7 +172,7 @@ async fn test_retrieving_batches() {
     start_mappers(&db).await;
 
     // Insert test data into batches table
-    db.execute(
+    create(&db,
         r"
         INSERT INTO batch (id, reference, sku, qty, eta, created_at, updated_at)
         VALUES ('1', 'batch1', 'sku1', 100, NULL, '2025-12-08T00:00:00', '2025-12-08T00:00:00'),
@@ -175,7 +184,7 @@ async fn test_retrieving_batches() {
 
     // Retrieve batches
     let batches: Vec<batches::Batch> =
-        read::<&SqlitePool, batches::Batch>(&db, &batches::Batch::select_sql(None))
+        read::<&DbPool, batches::Batch>(&db, &batches::Batch::select_sql(None))
             .await
             .unwrap();
 
@@ -212,6 +221,8 @@ async fn test_saving_batches() {
         sku: "sku1".to_string(),
         qty: 100,
         eta: None,
+        warehouse: None,
+        arrived_at: None,
         created_at: chrono::NaiveDate::from_ymd_opt(2025, 12, 8)
             .unwrap()
             .and_hms_opt(0, 0, 0)
@@ -229,7 +240,7 @@ async fn test_saving_batches() {
     create(&db, &insert).await.unwrap();
 
     let fetched_batch: Vec<batches::Batch> =
-        read::<&SqlitePool, batches::Batch>(&db, &batches::Batch::select_sql(None))
+        read::<&DbPool, batches::Batch>(&db, &batches::Batch::select_sql(None))
             .await
             .unwrap();
 
@@ -250,6 +261,8 @@ async fn test_saving_allocations() {
         sku: "sku1".to_string(),
         qty: 100,
         eta: None,
+        warehouse: None,
+        arrived_at: None,
         created_at: chrono::NaiveDate::from_ymd_opt(2025, 12, 8)
             .unwrap()
             .and_hms_opt(0, 0, 0)
@@ -264,6 +277,7 @@ async fn test_saving_allocations() {
 
     let order_line = OrderLine {
         id: "order1".to_string(),
+        order_id: None,
         sku: "sku1".to_string(),
         qty: 10,
         created_at: chrono::NaiveDate::from_ymd_opt(2025, 12, 8)
@@ -282,6 +296,7 @@ async fn test_saving_allocations() {
         id: "1".to_string(),
         order_line_id: order_line.id.clone(),
         batch_id: batch.id.clone(),
+        qty: None,
         created_at: chrono::NaiveDate::from_ymd_opt(2025, 12, 8)
             .unwrap()
             .and_hms_opt(0, 0, 0)
@@ -299,12 +314,9 @@ async fn test_saving_allocations() {
     create(&db, &new_allocation.insert_sql()).await.unwrap();
 
     let fetched_allocation: Vec<allocations::Allocation> =
-        read::<&SqlitePool, allocations::Allocation>(
-            &db,
-            &allocations::Allocation::select_sql(None),
-        )
-        .await
-        .unwrap();
+        read::<&DbPool, allocations::Allocation>(&db, &allocations::Allocation::select_sql(None))
+            .await
+            .unwrap();
 
     assert!(fetched_allocation == vec![new_allocation]);
 }
@@ -314,7 +326,7 @@ async fn test_retrieving_allocations() {
     let db = in_memory_db().await;
     start_mappers(&db).await;
     // Insert test data into allocations table
-    db.execute(
+    create(&db,
         r"
         INSERT INTO order_line (id, sku, qty, created_at, updated_at) VALUES ('order1', 'sku1', 12, '2025-12-08T00:00:00', '2025-12-08T00:00:00')
     ",
@@ -322,7 +334,7 @@ async fn test_retrieving_allocations() {
     .await
     .unwrap();
 
-    db.execute(
+    create(&db,
         r"
         INSERT INTO batch (id, reference, sku, qty, eta, created_at, updated_at) VALUES ('1', 'batch1', 'sku1', 100, NULL, '2025-12-08T00:00:00', '2025-12-08T00:00:00')
     ",
@@ -330,7 +342,8 @@ async fn test_retrieving_allocations() {
     .await
     .unwrap();
 
-    db.execute(
+    create(
+        &db,
         r"
         INSERT INTO allocation (id, order_line_id, batch_id, created_at, updated_at)
         VALUES ('1', 'order1', '1', '2025-12-08T00:00:00', '2025-12-08T00:00:00')
@@ -340,16 +353,14 @@ async fn test_retrieving_allocations() {
     .unwrap();
 
     // Retrieve allocations
-    let allocations = read::<&SqlitePool, allocations::Allocation>(
-        &db,
-        &allocations::Allocation::select_sql(None),
-    )
-    .await
-    .unwrap();
+    let allocations =
+        read::<&DbPool, allocations::Allocation>(&db, &allocations::Allocation::select_sql(None))
+            .await
+            .unwrap();
 
     let id = format!("'{}'", allocations[0].order_line_id.clone());
 
-    let order_lines = read_one::<&SqlitePool, order_lines::OrderLine>(
+    let order_lines = read_one::<&DbPool, order_lines::OrderLine>(
         &db,
         &order_lines::OrderLine::select_sql(Some(&order_lines::OrderLine::where_eq("id", &id))),
     )
@@ -0,0 +1,131 @@
+//! 與其他整合測試相同的情境，改在 Postgres 上執行；需設定 `TEST_DATABASE_URL`，否則略過
+use architecture::configures::AppConfig;
+use architecture::{arrivals, reservations, services};
+use chrono::{Duration, Utc};
+use serde_json::json;
+
+use crate::support::{TestApp, postgres_db};
+
+macro_rules! postgres_or_skip {
+    ($fixture:expr) => {
+        match $fixture.await {
+            Some(fixture) => fixture,
+            None => {
+                eprintln!("TEST_DATABASE_URL is not set, skipping Postgres test");
+                return;
+            }
+        }
+    };
+}
+
+#[tokio::test]
+async fn test_api_allocates_and_deallocates_on_postgres() {
+    let app = postgres_or_skip!(TestApp::postgres());
+
+    for (reference, eta) in [("pg-stock", None), ("pg-shipment", Some("2011-01-01"))] {
+        let (status, _) = app
+            .post_json(
+                "/add_batch",
+                json!({ "reference": reference, "sku": "PG-LAMP", "qty": 20, "eta": eta }),
+            )
+            .await;
+        assert_eq!(status, 201);
+    }
+
+    let (status, body) = app
+        .post_json(
+            "/allocate",
+            json!({ "id": "pg-order", "sku": "PG-LAMP", "qty": 5 }),
+        )
+        .await;
+    assert_eq!(status, 201);
+    assert_eq!(body["batch_ref"], "pg-stock");
+
+    let (status, product) = app.get_json("/products/PG-LAMP").await;
+    assert_eq!(status, 200);
+    assert_eq!(product["version_number"], 2);
+    let batches = product["batches"].as_array().unwrap();
+    assert_eq!(batches[0]["reference"], "pg-stock");
+    assert_eq!(batches[0]["allocated_quantity"], 5);
+    assert_eq!(batches[0]["available_quantity"], 15);
+    assert_eq!(batches[1]["eta"], "2011-01-01T00:00:00+00:00");
+
+    let (status, batch) = app.get_json("/batches/pg-stock").await;
+    assert_eq!(status, 200);
+    assert_eq!(batch["allocations"][0]["order_id"], "pg-order");
+
+    let (status, _) = app
+        .post_json("/deallocate", json!({ "id": "pg-order", "sku": "PG-LAMP" }))
+        .await;
+    assert_eq!(status, 200);
+
+    let (_, list) = app.get_json("/products?sku_prefix=PG-").await;
+    assert_eq!(list["total"], 1);
+}
+
+#[tokio::test]
+async fn test_stale_version_is_rejected_on_postgres() {
+    let db = postgres_or_skip!(postgres_db());
+
+    let mut tx = db.begin().await.unwrap();
+    services::add_batch("pg-batch", "PG-SOFA", 10, None, None, &mut tx)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+
+    let mut tx = db.begin().await.unwrap();
+    let stale = services::load_product(&mut tx, "PG-SOFA")
+        .await
+        .unwrap()
+        .unwrap();
+    tx.rollback().await.unwrap();
+
+    let mut tx = db.begin().await.unwrap();
+    services::save_version("PG-SOFA", stale.version_number, &mut tx)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+
+    let mut tx = db.begin().await.unwrap();
+    let err = services::save_version("PG-SOFA", stale.version_number, &mut tx)
+        .await
+        .unwrap_err();
+    tx.rollback().await.unwrap();
+    assert!(err.is_conflict());
+}
+
+#[tokio::test]
+async fn test_arrivals_and_reservations_on_postgres() {
+    let db = postgres_or_skip!(postgres_db());
+
+    let mut tx = db.begin().await.unwrap();
+    services::add_batch(
+        "pg-due",
+        "PG-DESK",
+        10,
+        Some(Utc::now() - Duration::hours(1)),
+        None,
+        &mut tx,
+    )
+    .await
+    .unwrap();
+    services::reserve(
+        "pg-hold",
+        "PG-DESK",
+        4,
+        Utc::now() - Duration::minutes(1),
+        &AppConfig::default(),
+        &mut tx,
+    )
+    .await
+    .unwrap();
+    tx.commit().await.unwrap();
+
+    let arrived = arrivals::check(&db).await.unwrap();
+    assert_eq!(arrived.len(), 1);
+    assert_eq!(arrived[0].reference, "pg-due");
+
+    let expired = reservations::sweep(&db).await.unwrap();
+    assert_eq!(expired.len(), 1);
+    assert_eq!(expired[0].order_id, "pg-hold");
+}
@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use architecture::configures::{AppConfig, RuntimeConfig};
+use architecture::reloader;
+use architecture::shutdown::Shutdown;
+
+use crate::support::ENV;
+
+const CONFIG: &str = r#"
+[database]
+database = "mysql"
+
+[logger]
+level = "info"
+"#;
+
+#[tokio::test]
+async fn test_watcher_applies_changed_log_level_and_timeout() {
+    let _env = ENV.lock().await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("custom.toml");
+    std::fs::write(&path, CONFIG).unwrap();
+
+    let runtime = RuntimeConfig::detached(&AppConfig::load_from(&path).unwrap());
+    let shutdown = Shutdown::new();
+    let watcher = reloader::spawn_watcher(
+        path.clone(),
+        runtime.clone(),
+        Duration::from_millis(20),
+        shutdown.clone(),
+    );
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // 設定錯誤時保留目前的設定
+    std::fs::write(&path, CONFIG.replace("\"info\"", "\"verbose\"")).unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(runtime.log_level().current(), "info");
+
+    std::fs::write(
+        &path,
+        format!(
+            "{}\n[server]\nrequest_timeout_seconds = 30\n",
+            CONFIG.replace("\"info\"", "\"warn\"")
+        ),
+    )
+    .unwrap();
+
+    for _ in 0..100 {
+        if runtime.log_level().current() == "warn" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    shutdown.trigger();
+    tokio::time::timeout(Duration::from_secs(1), watcher)
+        .await
+        .expect("watcher should stop on shutdown")
+        .unwrap();
+
+    assert_eq!(runtime.log_level().current(), "warn");
+    assert_eq!(runtime.server().request_timeout(), Duration::from_secs(30));
+}
@@ -1,3 +1,4 @@
+use architecture::repositories::DbPool;
 use architecture::repositories::{create, read_one, read_to_json};
 use architecture::{
     chapter1,
@@ -5,20 +6,20 @@ use architecture::{
 };
 use chrono::Utc;
 use serde_json::json;
-use sqlx::Executor;
-use sqlx::SqlitePool;
 use sqlx::sqlite::SqlitePoolOptions;
 
-async fn in_memory_db() -> SqlitePool {
+async fn in_memory_db() -> DbPool {
     SqlitePoolOptions::new()
         .max_connections(1)
         .connect("sqlite::memory:")
         .await
         .unwrap()
+        .into()
 }
 
-async fn start_mappers(db: &SqlitePool) {
-    db.execute(
+async fn start_mappers(db: &DbPool) {
+    create(
+        db,
         "CREATE TABLE test_table (
                 id INTEGER PRIMARY KEY,
                 name TEXT,
@@ -29,7 +30,8 @@ async fn start_mappers(db: &SqlitePool) {
     .await
     .unwrap();
 
-    db.execute(
+    create(
+        db,
         r"
         CREATE TABLE batch (
             id TEXT PRIMARY KEY,
@@ -37,6 +39,8 @@ async fn start_mappers(db: &SqlitePool) {
             sku TEXT,
             qty INTEGER,
             eta TEXT,
+            warehouse TEXT,
+            arrived_at TEXT,
             created_at TEXT,
             updated_at TEXT
         )",
@@ -44,10 +48,12 @@ async fn start_mappers(db: &SqlitePool) {
     .await
     .unwrap();
 
-    db.execute(
+    create(
+        db,
         r"
         CREATE TABLE order_line (
             id TEXT PRIMARY KEY,
+            order_id TEXT,
             sku TEXT,
             qty INTEGER,
             created_at TEXT,
@@ -57,12 +63,14 @@ async fn start_mappers(db: &SqlitePool) {
     .await
     .unwrap();
 
-    db.execute(
+    create(
+        db,
         r"
         CREATE TABLE allocation (
             id TEXT PRIMARY KEY,
             order_line_id TEXT,
             batch_id TEXT,
+            qty INTEGER,
             created_at TEXT,
             updated_at TEXT
         )",
@@ -81,6 +89,8 @@ async fn test_repository_can_save_a_batch() {
         sku: "RUSTY-SOAPDISH".to_string(),
         qty: 100,
         eta: None,
+        warehouse: None,
+        arrived_at: None,
         id: xid::new().to_string(),
         created_at: Utc::now(),
         updated_at: Utc::now(),
@@ -88,7 +98,7 @@ async fn test_repository_can_save_a_batch() {
 
     create(&db, &batch.insert_sql()).await.unwrap();
 
-    let fetched_batch = read_one::<&SqlitePool, Batch>(&db, &Batch::select_sql(None))
+    let fetched_batch = read_one::<&DbPool, Batch>(&db, &Batch::select_sql(None))
         .await
         .unwrap();
 
@@ -109,13 +119,13 @@ async fn test_repository_can_retrieve_a_batch_with_allocations() {
     insert_batch(&db, "batch2".to_string()).await;
     insert_allocation(&db, order_line_id.clone(), batch_id.clone()).await;
 
-    let fetched_batch = read_one::<&SqlitePool, Batch>(
+    let fetched_batch = read_one::<&DbPool, Batch>(
         &db,
         &Batch::select_sql(Some(&format!("id = '{}'", batch_id))),
     )
     .await
     .unwrap();
-    let fetched_order_line = read_one::<&SqlitePool, OrderLine>(
+    let fetched_order_line = read_one::<&DbPool, OrderLine>(
         &db,
         &OrderLine::select_sql(Some(&format!("id = '{}'", order_line_id))),
     )
@@ -140,9 +150,10 @@ async fn test_repository_can_retrieve_a_batch_with_allocations() {
     assert_eq!(fetched_order_line.qty, expected_order.qty);
 }
 
-async fn insert_order_line(db: &SqlitePool) -> String {
+async fn insert_order_line(db: &DbPool) -> String {
     let order_line = OrderLine {
         id: "order1".to_string(),
+        order_id: None,
         sku: "GENERIC-SOFA".to_string(),
         qty: 12,
         created_at: Utc::now(),
@@ -153,12 +164,14 @@ async fn insert_order_line(db: &SqlitePool) -> String {
     order_line.id
 }
 
-async fn insert_batch(db: &SqlitePool, batch_id: String) -> String {
+async fn insert_batch(db: &DbPool, batch_id: String) -> String {
     let batch = Batch {
         reference: batch_id,
         sku: "GENERIC-SOFA".to_string(),
         qty: 100,
         eta: None,
+        warehouse: None,
+        arrived_at: None,
         id: xid::new().to_string(),
         created_at: Utc::now(),
         updated_at: Utc::now(),
@@ -169,10 +182,11 @@ async fn insert_batch(db: &SqlitePool, batch_id: String) -> String {
     batch.id
 }
 
-async fn insert_allocation(db: &SqlitePool, order_line_id: String, batch_id: String) -> String {
+async fn insert_allocation(db: &DbPool, order_line_id: String, batch_id: String) -> String {
     let allocation = Allocation {
         order_line_id,
         batch_id,
+        qty: None,
         id: xid::new().to_string(),
         created_at: Utc::now(),
         updated_at: Utc::now(),
@@ -187,7 +201,8 @@ async fn insert_allocation(db: &SqlitePool, order_line_id: String, batch_id: Str
 async fn test_query_to_json_try_decode() {
     let db = in_memory_db().await;
     start_mappers(&db).await;
-    db.execute(
+    create(
+        &db,
         "INSERT INTO test_table (name, age, active) VALUES
             ('Alice', 30, 1),
             ('Bob', NULL, 0)",
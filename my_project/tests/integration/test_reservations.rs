@@ -0,0 +1,254 @@
+use architecture::configures::AppConfig;
+use architecture::entities::reservations::{self, Reservation};
+use architecture::repositories::DbPool;
+use architecture::repositories::{quote, read_one, read_to_json};
+use architecture::shutdown::Shutdown;
+use architecture::{reservations as sweeper, services};
+use chrono::{Duration, Utc};
+use std::sync::Arc;
+
+use crate::support::memory_db;
+
+async fn add_batch(db: &DbPool, reference: &str, sku: &str, qty: u32) {
+    let mut tx = db.begin().await.unwrap();
+    services::add_batch(reference, sku, qty, None, None, &mut tx)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+}
+
+async fn reserve(db: &DbPool, order_id: &str, sku: &str, qty: u32, ttl: Duration) -> String {
+    let mut tx = db.begin().await.unwrap();
+    let batch_ref = services::reserve(
+        order_id,
+        sku,
+        qty,
+        Utc::now() + ttl,
+        &AppConfig::default(),
+        &mut tx,
+    )
+    .await
+    .unwrap();
+    tx.commit().await.unwrap();
+    batch_ref
+}
+
+async fn allocated_quantity(db: &DbPool, batch_ref: &str) -> i64 {
+    let mut tx = db.begin().await.unwrap();
+    let batches = services::load_batches(&mut tx, "RESERVED-LAMP")
+        .await
+        .unwrap();
+    batches
+        .iter()
+        .find(|b| b.reference == batch_ref)
+        .map(|b| b.allocated_quantity() as i64)
+        .unwrap()
+}
+
+async fn status(db: &DbPool, order_id: &str) -> String {
+    read_one::<&DbPool, Reservation>(
+        db,
+        &Reservation::select_sql(Some(&format!("order_id = {}", quote(order_id)))),
+    )
+    .await
+    .unwrap()
+    .unwrap()
+    .status
+}
+
+#[tokio::test]
+async fn test_sweeper_releases_expired_reservations_only() {
+    let db = memory_db().await;
+    add_batch(&db, "batch1", "RESERVED-LAMP", 100).await;
+
+    let batch_ref = reserve(
+        &db,
+        "order-expired",
+        "RESERVED-LAMP",
+        10,
+        Duration::seconds(-1),
+    )
+    .await;
+    reserve(
+        &db,
+        "order-held",
+        "RESERVED-LAMP",
+        20,
+        Duration::minutes(10),
+    )
+    .await;
+    reserve(
+        &db,
+        "order-confirmed",
+        "RESERVED-LAMP",
+        30,
+        Duration::minutes(10),
+    )
+    .await;
+
+    let mut tx = db.begin().await.unwrap();
+    services::confirm_reservation("order-confirmed", "RESERVED-LAMP", &mut tx)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+
+    assert_eq!(allocated_quantity(&db, &batch_ref).await, 60);
+
+    let expired = sweeper::sweep(&db).await.unwrap();
+    assert_eq!(expired.len(), 1);
+    assert_eq!(expired[0].order_id, "order-expired");
+    assert_eq!(expired[0].sku, "RESERVED-LAMP");
+    assert_eq!(expired[0].qty, 10);
+
+    assert_eq!(allocated_quantity(&db, &batch_ref).await, 50);
+    assert_eq!(status(&db, "order-expired").await, reservations::EXPIRED);
+    assert_eq!(status(&db, "order-held").await, reservations::HELD);
+    assert_eq!(
+        status(&db, "order-confirmed").await,
+        reservations::CONFIRMED
+    );
+
+    let lines = read_to_json(
+        &db,
+        "SELECT id FROM order_line WHERE order_id = 'order-expired'",
+    )
+    .await
+    .unwrap();
+    assert!(lines.is_empty());
+
+    // 再次執行不會重複釋放
+    assert!(sweeper::sweep(&db).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_cannot_confirm_an_expired_reservation() {
+    let db = memory_db().await;
+    add_batch(&db, "batch1", "RESERVED-LAMP", 100).await;
+    reserve(&db, "order1", "RESERVED-LAMP", 10, Duration::seconds(-1)).await;
+
+    let mut tx = db.begin().await.unwrap();
+    let err = services::confirm_reservation("order1", "RESERVED-LAMP", &mut tx)
+        .await
+        .unwrap_err();
+    tx.rollback().await.unwrap();
+
+    assert_eq!(
+        err,
+        "Reservation for order order1 and sku RESERVED-LAMP has expired"
+    );
+}
+
+#[tokio::test]
+async fn test_sweeper_skips_reservation_released_by_deallocate() {
+    let db = memory_db().await;
+    add_batch(&db, "batch1", "RESERVED-LAMP", 100).await;
+    reserve(
+        &db,
+        "order-gone",
+        "RESERVED-LAMP",
+        10,
+        Duration::seconds(-1),
+    )
+    .await;
+    let batch_ref = reserve(
+        &db,
+        "order-expired",
+        "RESERVED-LAMP",
+        20,
+        Duration::seconds(-1),
+    )
+    .await;
+
+    let mut tx = db.begin().await.unwrap();
+    services::deallocate("order-gone", "RESERVED-LAMP", &mut tx)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+
+    // 已釋放的保留不能確認
+    let mut tx = db.begin().await.unwrap();
+    let err = services::confirm_reservation("order-gone", "RESERVED-LAMP", &mut tx)
+        .await
+        .unwrap_err();
+    tx.rollback().await.unwrap();
+    assert_eq!(
+        err,
+        "Reservation for order order-gone and sku RESERVED-LAMP has expired"
+    );
+
+    // 不會卡住其他保留，也不會重複發布事件
+    let expired = sweeper::sweep(&db).await.unwrap();
+    assert_eq!(expired.len(), 1);
+    assert_eq!(expired[0].order_id, "order-expired");
+    assert_eq!(allocated_quantity(&db, &batch_ref).await, 0);
+    assert_eq!(status(&db, "order-gone").await, reservations::EXPIRED);
+    assert_eq!(status(&db, "order-expired").await, reservations::EXPIRED);
+    assert!(sweeper::sweep(&db).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_cannot_confirm_reservation_without_allocation() {
+    let db = memory_db().await;
+    add_batch(&db, "batch1", "RESERVED-LAMP", 100).await;
+    reserve(&db, "order1", "RESERVED-LAMP", 10, Duration::minutes(10)).await;
+
+    let mut tx = db.begin().await.unwrap();
+    services::deallocate("order1", "RESERVED-LAMP", &mut tx)
+        .await
+        .unwrap();
+    let err = services::confirm_reservation("order1", "RESERVED-LAMP", &mut tx)
+        .await
+        .unwrap_err();
+    tx.rollback().await.unwrap();
+
+    assert_eq!(
+        err,
+        "Reservation for order order1 and sku RESERVED-LAMP no longer holds an allocation"
+    );
+}
+
+#[tokio::test]
+async fn test_cannot_reserve_an_allocated_order() {
+    let db = memory_db().await;
+    add_batch(&db, "batch1", "RESERVED-LAMP", 100).await;
+
+    let mut tx = db.begin().await.unwrap();
+    services::allocate_order(
+        "order1",
+        &[("RESERVED-LAMP".to_string(), 10)],
+        None,
+        &AppConfig::default(),
+        &mut tx,
+    )
+    .await
+    .unwrap();
+    let err = services::reserve(
+        "order1",
+        "RESERVED-LAMP",
+        10,
+        Utc::now() + Duration::minutes(10),
+        &AppConfig::default(),
+        &mut tx,
+    )
+    .await
+    .unwrap_err();
+    tx.rollback().await.unwrap();
+
+    assert_eq!(
+        err.to_string(),
+        "Order order1 is already allocated for sku RESERVED-LAMP"
+    );
+}
+
+#[tokio::test]
+async fn test_sweeper_stops_on_shutdown() {
+    let db = memory_db().await;
+    let shutdown = Shutdown::new();
+    let handle = sweeper::spawn_sweeper(db, Arc::new(AppConfig::default()), shutdown.clone());
+
+    shutdown.trigger();
+    tokio::time::timeout(std::time::Duration::from_secs(1), handle)
+        .await
+        .expect("sweeper should stop on shutdown")
+        .unwrap();
+}
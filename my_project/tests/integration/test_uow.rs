@@ -1,11 +1,17 @@
+use architecture::api_base::api_errors::ApiError;
 use architecture::chapter1;
 use architecture::configures;
 use architecture::entities::batches;
 use architecture::entities::products;
+use architecture::repositories::create;
 use architecture::repositories::read;
 use architecture::repositories::read_one;
 use architecture::repositories::update;
-use sqlx::SqliteConnection;
+use architecture::repositories::{DbPool, DbTransaction};
+use architecture::services;
+use axum::response::IntoResponse;
+
+use crate::support::{memory_db, temp_db};
 
 fn random_suffix() -> String {
     let s = xid::new().to_string();
@@ -29,7 +35,7 @@ fn random_order_id(name: &str) -> String {
 }
 
 async fn insert_batch(
-    db: &sqlx::SqlitePool,
+    db: &DbPool,
     batch_ref: &str,
     sku: &str,
     qty: i32,
@@ -41,7 +47,7 @@ async fn insert_batch(
         sku, version
     );
 
-    sqlx::query(&product_ent).execute(db).await.unwrap();
+    create(db, &product_ent).await.unwrap();
 
     let eta_val = match eta {
         Some(date_str) => format!("'{}'", date_str),
@@ -53,11 +59,11 @@ async fn insert_batch(
         batch_ref, sku, qty, eta_val
     );
 
-    sqlx::query(&batch_ent).execute(db).await.unwrap();
+    create(db, &batch_ent).await.unwrap();
 }
 
 async fn try_to_allocate(
-    db: &sqlx::SqlitePool,
+    db: &DbPool,
     order_id: &str,
     sku: &str,
     barrier: std::sync::Arc<tokio::sync::Barrier>,
@@ -73,8 +79,8 @@ async fn try_to_allocate(
     let where_clause_string = format!("sku = '{}'", sku);
     let where_clause = Some(where_clause_string.as_str());
 
-    let product_ent = read_one::<&mut SqliteConnection, products::Product>(
-        &mut *tx,
+    let product_ent = read_one::<&mut DbTransaction, products::Product>(
+        &mut tx,
         &products::Product::select_sql(where_clause),
     )
     .await
@@ -83,8 +89,8 @@ async fn try_to_allocate(
     barrier.wait().await;
 
     if let Some(ent) = product_ent {
-        let batche_ents = read::<&mut SqliteConnection, batches::Batch>(
-            &mut *tx,
+        let batche_ents = read::<&mut DbTransaction, batches::Batch>(
+            &mut tx,
             &batches::Batch::select_sql(where_clause),
         )
         .await
@@ -98,10 +104,10 @@ async fn try_to_allocate(
         let res = ent.build(batches).allocate(&order);
         match res {
             Ok(batches_ref) => {
-                let batch_ref = batches_ref.unwrap();
+                let batch_ref = batches_ref.into_new().unwrap();
 
-                let product_res = update::<&mut SqliteConnection>(
-                    &mut *tx,
+                let product_res = update::<&mut DbTransaction>(
+                    &mut tx,
                     &format!(
                         "UPDATE product SET version_number = {} WHERE sku = '{}' AND version_number = {}",
                         batch_ref.1, sku, ent.version_number
@@ -110,8 +116,8 @@ async fn try_to_allocate(
                 .await;
                 match product_res {
                     Ok(result) => {
-                        update::<&mut SqliteConnection>(
-                            &mut *tx,
+                        update::<&mut DbTransaction>(
+                            &mut tx,
                             &format!(
                                 "UPDATE batch SET qty = qty - 10 WHERE reference = '{}'",
                                 batch_ref.0
@@ -122,7 +128,7 @@ async fn try_to_allocate(
 
                         println!("Order id {} allocated to batch {}", order_id, batch_ref.0);
 
-                        if result.rows_affected() == 1 {
+                        if result == 1 {
                             tx.commit().await.unwrap();
                         } else {
                             tx.rollback().await.unwrap();
@@ -147,12 +153,8 @@ async fn try_to_allocate(
 }
 
 #[tokio::test]
-#[ignore]
 async fn test_concurrent_updates_to_version_are_not_allowed() {
-    let db = configures::AppConfig::load()
-        .database
-        .get_connection()
-        .await;
+    let (_dir, db) = temp_db().await;
 
     let sku = random_sku("");
     let batch = random_batch_ref("");
@@ -189,7 +191,7 @@ async fn test_concurrent_updates_to_version_are_not_allowed() {
         panic!("Task 2 failed: {:?}", e);
     }
 
-    let version_number: Option<(i32,)> = read_one::<&sqlx::SqlitePool, (i32,)>(
+    let version_number: Option<(i32,)> = read_one::<&DbPool, (i32,)>(
         &db,
         &format!(
             "SELECT MAX(version_number) FROM product WHERE sku = '{}'",
@@ -201,3 +203,70 @@ async fn test_concurrent_updates_to_version_are_not_allowed() {
 
     assert_eq!(version_number.unwrap().0, 2);
 }
+
+#[tokio::test]
+async fn test_stale_version_is_rejected_as_concurrency_conflict() {
+    let db = memory_db().await;
+    let sku = random_sku("");
+
+    let mut tx = db.begin().await.unwrap();
+    services::add_batch(&random_batch_ref(""), &sku, 100, None, None, &mut tx)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+
+    // 兩個命令讀到同一個版本
+    let mut tx = db.begin().await.unwrap();
+    let first = services::load_product(&mut tx, &sku)
+        .await
+        .unwrap()
+        .unwrap();
+    let second = services::load_product(&mut tx, &sku)
+        .await
+        .unwrap()
+        .unwrap();
+    tx.rollback().await.unwrap();
+
+    let mut tx = db.begin().await.unwrap();
+    services::save_version(&sku, first.version_number, &mut tx)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+
+    let mut tx = db.begin().await.unwrap();
+    let err = services::save_version(&sku, second.version_number, &mut tx)
+        .await
+        .unwrap_err();
+    tx.rollback().await.unwrap();
+
+    assert!(err.is_conflict());
+    assert_eq!(
+        err.to_string(),
+        format!("Version number conflict for sku {}", sku)
+    );
+
+    let response = ApiError::from(err).into_response();
+    assert_eq!(response.status(), 409);
+
+    let version_number: Option<(i32,)> = read_one::<&DbPool, (i32,)>(
+        &db,
+        &format!("SELECT version_number FROM product WHERE sku = '{}'", sku),
+    )
+    .await
+    .unwrap();
+    assert_eq!(version_number.unwrap().0, 2);
+}
+
+#[test]
+fn test_messagebus_retry_backoff_is_bounded() {
+    let config = configures::AppConfig::builder()
+        .messagebus(configures::MessageBusConfig {
+            max_retries: None,
+            retry_backoff_ms: Some(10),
+        })
+        .build();
+
+    assert_eq!(config.messagebus.max_retries(), 3);
+    assert!(config.messagebus.backoff(1) > config.messagebus.backoff(0));
+    assert_eq!(config.messagebus.backoff(50), config.messagebus.backoff(10));
+}
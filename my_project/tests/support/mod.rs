@@ -0,0 +1,273 @@
+//! 測試共用的 fixture：每個測試各自的資料庫、router 與同步用的暫存目錄，
+//! 測試可以平行執行而不互相影響
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use architecture::auth::{self, TokenKind};
+use architecture::configures::{AppConfig, LoggerConfig, Role, RuntimeConfig, SecretConfig};
+use architecture::health::Workers;
+use architecture::repositories::DbPool;
+use axum::{Router, body::Body, extract::Request};
+use http_body_util::BodyExt;
+use serde_json::Value;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+use tempfile::TempDir;
+use tower::ServiceExt;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::prelude::*;
+
+/// 已執行 migration 的記憶體資料庫，只有一個連線（每個連線都是獨立的記憶體資料庫）
+pub async fn memory_db() -> DbPool {
+    let db: DbPool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap()
+        .into();
+
+    db.migrate().await.unwrap();
+    db
+}
+
+/// 已執行 migration 的暫存檔案資料庫，可開多個連線；TempDir 釋放時一併刪除
+pub async fn temp_db() -> (TempDir, DbPool) {
+    let dir = tempfile::tempdir().unwrap();
+    let options = SqliteConnectOptions::from_str(&format!(
+        "sqlite://{}",
+        dir.path().join("test.db").display()
+    ))
+    .unwrap()
+    .journal_mode(SqliteJournalMode::Wal)
+    .busy_timeout(Duration::from_secs(5))
+    .create_if_missing(true);
+
+    let db: DbPool = SqlitePoolOptions::new()
+        .max_connections(8)
+        .connect_with(options)
+        .await
+        .unwrap()
+        .into();
+
+    db.migrate().await.unwrap();
+    (dir, db)
+}
+
+/// 設定 `TEST_DATABASE_URL`（例如 `postgres://postgres@localhost/postgres`）時才跑 Postgres 測試，
+/// 每次建立一個 `test_` 開頭的 schema 並執行 migration；未設定時回傳 None，由測試自行略過
+pub async fn postgres_db() -> Option<DbPool> {
+    let url = std::env::var("TEST_DATABASE_URL").ok()?;
+    let schema = format!("test_{}", uuid::Uuid::new_v4().simple());
+
+    let admin = PgPoolOptions::new()
+        .max_connections(1)
+        .connect(&url)
+        .await
+        .unwrap();
+    sqlx::query(&format!("CREATE SCHEMA {}", schema))
+        .execute(&admin)
+        .await
+        .unwrap();
+    admin.close().await;
+
+    let options = PgConnectOptions::from_str(&url)
+        .unwrap()
+        .options([("search_path", schema.as_str())]);
+    let db: DbPool = PgPoolOptions::new()
+        .max_connections(8)
+        .connect_with(options)
+        .await
+        .unwrap()
+        .into();
+
+    db.migrate().await.unwrap();
+    Some(db)
+}
+
+/// `APP_` 環境變數是整個行程共用的，會讀設定檔的測試要先取得這把鎖
+pub static ENV: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// 以獨立的暫存資料庫與程式建立的設定組成的 router
+pub struct TestApp {
+    pub db: DbPool,
+    pub route: Router,
+    pub runtime: RuntimeConfig,
+    pub workers: Workers,
+    config: Arc<AppConfig>,
+    _dir: Option<TempDir>,
+}
+
+impl TestApp {
+    pub async fn new() -> Self {
+        Self::with_config(AppConfig::default()).await
+    }
+
+    pub async fn with_config(config: AppConfig) -> Self {
+        let (dir, db) = temp_db().await;
+        Self::on(db, Some(dir), config).await
+    }
+
+    /// 在 Postgres 上建立 router，未設定 `TEST_DATABASE_URL` 時回傳 None
+    pub async fn postgres() -> Option<Self> {
+        let db = postgres_db().await?;
+        Some(Self::on(db, None, AppConfig::default()).await)
+    }
+
+    /// 沒有指定金鑰時使用測試用的金鑰
+    async fn on(db: DbPool, dir: Option<TempDir>, mut config: AppConfig) -> Self {
+        let secret = &mut config.secret;
+        secret
+            .jwt_secret
+            .get_or_insert_with(|| "test-jwt-secret".to_string());
+        secret
+            .refresh_secret
+            .get_or_insert_with(|| "test-refresh-secret".to_string());
+
+        let config = Arc::new(config);
+        let runtime = RuntimeConfig::detached(&config);
+        let workers = Workers::default();
+        let route = architecture::sitemaps::sitemap(
+            db.clone(),
+            config.clone(),
+            runtime.clone(),
+            workers.clone(),
+        )
+        .await;
+
+        TestApp {
+            db,
+            route,
+            runtime,
+            workers,
+            config,
+            _dir: dir,
+        }
+    }
+
+    /// 以這個 app 的金鑰簽發 access token
+    pub fn token(&self, roles: &[Role]) -> String {
+        auth::issue(&self.config, "tester", roles, TokenKind::Access).unwrap()
+    }
+
+    pub fn secret(&self) -> &SecretConfig {
+        &self.config.secret
+    }
+
+    /// 需要檢查 header 時直接送出完整的 request
+    pub async fn send(&self, request: Request) -> axum::response::Response {
+        self.route.clone().oneshot(request).await.unwrap()
+    }
+
+    /// 帶著擁有所有角色的 token，權限檢查另有專門的測試
+    pub async fn request(&self, method: &str, uri: &str, data: Option<Value>) -> (u16, Value) {
+        let body = match data {
+            Some(data) => Body::from(data.to_string()),
+            None => Body::empty(),
+        };
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Content-Type", "application/json")
+            .header(
+                "Authorization",
+                format!(
+                    "Bearer {}",
+                    self.token(&[Role::Inventory, Role::Sales, Role::Admin])
+                ),
+            )
+            .body(body)
+            .unwrap();
+
+        let res = self.send(request).await;
+        let status = res.status().as_u16();
+
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        (
+            status,
+            serde_json::from_slice::<Value>(&body).unwrap_or(Value::Null),
+        )
+    }
+
+    pub async fn get_json(&self, uri: &str) -> (u16, Value) {
+        self.request("GET", uri, None).await
+    }
+
+    pub async fn post_json(&self, uri: &str, data: Value) -> (u16, Value) {
+        self.request("POST", uri, Some(data)).await
+    }
+}
+
+/// 檔案同步用的來源與目標目錄
+pub struct SyncDirs {
+    pub source: PathBuf,
+    pub target: PathBuf,
+    _root: TempDir,
+}
+
+/// 建立空的來源與目標暫存目錄
+pub fn sync_dirs() -> SyncDirs {
+    let root = tempfile::tempdir().unwrap();
+    let source = root.path().join("source");
+    let target = root.path().join("target");
+    std::fs::create_dir_all(&source).unwrap();
+    std::fs::create_dir_all(&target).unwrap();
+
+    SyncDirs {
+        source,
+        target,
+        _root: root,
+    }
+}
+
+impl SyncDirs {
+    pub fn write_source(&self, name: &str, content: &str) {
+        std::fs::write(self.source.join(name), content).unwrap();
+    }
+
+    pub fn write_target(&self, name: &str, content: &str) {
+        std::fs::write(self.target.join(name), content).unwrap();
+    }
+}
+
+/// 收集 JSON layer 寫出的每一行
+#[derive(Clone, Default)]
+pub struct LogCapture(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for LogCapture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogCapture {
+    type Writer = LogCapture;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+impl LogCapture {
+    /// 在目前的執行緒安裝只寫到這裡的 JSON subscriber
+    pub fn install(&self) -> tracing::subscriber::DefaultGuard {
+        let subscriber =
+            tracing_subscriber::registry().with(LoggerConfig::json_layer(self.clone()));
+        tracing::subscriber::set_default(subscriber)
+    }
+
+    pub fn lines(&self) -> Vec<Value> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("each line is a JSON object"))
+            .collect()
+    }
+}
@@ -1,35 +1,27 @@
 pub mod e2e;
 pub mod integration;
+pub mod support;
 pub mod unit;
 
 use architecture::chapter2;
 
 #[test]
 fn test_sync() {
-    let source = "tests/source";
-    let target = "tests/target";
-
-    // Prepare test directories
-    std::fs::create_dir_all(source).unwrap();
-    std::fs::create_dir_all(target).unwrap();
+    let dirs = support::sync_dirs();
 
     // Create test files in source
-    std::fs::write(format!("{}/file1.txt", source), "Hello World").unwrap();
-    std::fs::write(format!("{}/file2.txt", source), "Rust Programming").unwrap();
+    dirs.write_source("file1.txt", "Hello World");
+    dirs.write_source("file2.txt", "Rust Programming");
 
     // Create test files in target
-    std::fs::write(format!("{}/file1.txt", target), "Old Content").unwrap();
-    std::fs::write(format!("{}/file3.txt", target), "Rust Programming").unwrap();
+    dirs.write_target("file1.txt", "Old Content");
+    dirs.write_target("file3.txt", "Rust Programming");
 
     // Perform sync
-    chapter2::sync(source, target);
+    chapter2::sync(dirs.source.to_str().unwrap(), dirs.target.to_str().unwrap());
 
     // Verify results
-    assert!(std::path::Path::new(&format!("{}/file1.txt", target)).exists());
-    assert!(std::path::Path::new(&format!("{}/file2.txt", target)).exists());
-    assert!(!std::path::Path::new(&format!("{}/file3.txt", target)).exists());
-
-    // Clean up test directories
-    std::fs::remove_dir_all(source).unwrap();
-    std::fs::remove_dir_all(target).unwrap();
+    assert!(dirs.target.join("file1.txt").exists());
+    assert!(dirs.target.join("file2.txt").exists());
+    assert!(!dirs.target.join("file3.txt").exists());
 }
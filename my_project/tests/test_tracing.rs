@@ -0,0 +1,183 @@
+//! OTLP 匯出的測試獨立成一個測試程式：SQLite 的 worker 執行緒只看得到全域的 subscriber，
+//! 用 set_default 只掛在測試執行緒上時，父 span 的參照會被放到別的 subscriber 而永遠不會結束，
+//! 全域 subscriber 又會干擾其他擷取 log 的測試
+#[allow(dead_code)]
+#[path = "support/mod.rs"]
+mod support;
+
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use architecture::configures::{AppConfig, otel_layer};
+use architecture::{events, messagebus};
+use axum::body::Body;
+use axum::extract::Request;
+use opentelemetry::trace::TraceId;
+use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider, SpanData};
+use serde_json::json;
+use tracing_subscriber::layer::SubscriberExt;
+
+use support::TestApp;
+
+/// 以記憶體 exporter 取代 collector，span 結束時同步寫入；各測試以自己的 trace id 篩選
+static RECORDER: LazyLock<(InMemorySpanExporter, SdkTracerProvider)> = LazyLock::new(|| {
+    let exporter = InMemorySpanExporter::default();
+    let provider = SdkTracerProvider::builder()
+        .with_simple_exporter(exporter.clone())
+        .build();
+    let subscriber = tracing_subscriber::registry().with(otel_layer(&provider, "test"));
+    tracing::subscriber::set_global_default(subscriber).unwrap();
+    (exporter, provider)
+});
+
+fn attribute(span: &SpanData, key: &str) -> Option<String> {
+    span.attributes
+        .iter()
+        .find(|kv| kv.key.as_str() == key)
+        .map(|kv| kv.value.as_str().into_owned())
+}
+
+/// worker 執行緒回覆後才放開它持有的 span，外層 span 可能稍晚才結束
+async fn trace(root: &str, key: &str, value: &str) -> Vec<SpanData> {
+    for _ in 0..100 {
+        let spans = RECORDER.0.get_finished_spans().unwrap();
+        let trace_id: Option<TraceId> = spans
+            .iter()
+            .find(|span| span.name == root && attribute(span, key).as_deref() == Some(value))
+            .map(|span| span.span_context.trace_id());
+        if let Some(trace_id) = trace_id {
+            return spans
+                .into_iter()
+                .filter(|span| span.span_context.trace_id() == trace_id)
+                .collect();
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("span {root} was not exported");
+}
+
+fn named<'a>(spans: &'a [SpanData], name: &str) -> Vec<&'a SpanData> {
+    spans.iter().filter(|span| span.name == name).collect()
+}
+
+#[tokio::test]
+async fn test_allocate_request_exports_repository_spans() {
+    LazyLock::force(&RECORDER);
+    let app = TestApp::new().await;
+    let (status, _) = app
+        .post_json(
+            "/add_batch",
+            json!({ "reference": "otel-batch", "sku": "OTEL-LAMP", "qty": 10 }),
+        )
+        .await;
+    assert_eq!(status, 201);
+
+    let (status, _) = app
+        .post_json(
+            "/allocate",
+            json!({ "id": "otel-order", "sku": "OTEL-LAMP", "qty": 1 }),
+        )
+        .await;
+    assert_eq!(status, 201);
+
+    let spans = trace("sitemap", "order_id", "otel-order").await;
+    let request = named(&spans, "sitemap");
+    assert_eq!(request.len(), 1);
+    assert_eq!(attribute(request[0], "sku").as_deref(), Some("OTEL-LAMP"));
+
+    let repository: Vec<_> = spans
+        .iter()
+        .filter(|span| span.name.starts_with("repository."))
+        .collect();
+    assert!(!repository.is_empty());
+    assert!(repository.iter().all(|span| {
+        attribute(span, "db.operation.name").is_some()
+            && attribute(span, "db.collection.name").is_some()
+            && attribute(span, "db.statement").is_none()
+    }));
+    assert!(repository.iter().any(|span| {
+        attribute(span, "db.operation.name").as_deref() == Some("INSERT")
+            && attribute(span, "db.collection.name").as_deref() == Some("allocation")
+    }));
+    // 條件中的字面值不會出現在匯出的 span
+    assert!(
+        spans
+            .iter()
+            .flat_map(|span| {
+                span.attributes
+                    .iter()
+                    .chain(span.events.iter().flat_map(|event| event.attributes.iter()))
+            })
+            .all(|kv| !kv.value.as_str().contains("'otel-order'"))
+    );
+}
+
+#[tokio::test]
+async fn test_message_bus_spans_share_one_trace() {
+    LazyLock::force(&RECORDER);
+    let app = TestApp::new().await;
+    let (status, _) = app
+        .post_json(
+            "/add_batch",
+            json!({ "reference": "otel-bus-batch", "sku": "OTEL-DESK", "qty": 10 }),
+        )
+        .await;
+    assert_eq!(status, 201);
+
+    let event = events::Event::AllocateRequired(events::AllocateRequired {
+        order_id: "otel-bus-order".to_string(),
+        sku: "OTEL-DESK".to_string(),
+        qty: 1,
+    });
+    messagebus::headle(
+        events::Envelope::new(event, "otel-bus-7"),
+        &app.db,
+        &AppConfig::default(),
+    )
+    .await
+    .unwrap();
+
+    let spans = trace("messagebus", "request_id", "otel-bus-7").await;
+    assert_eq!(named(&spans, "dispatch").len(), 1);
+    let handler = named(&spans, "handler.allocate");
+    assert_eq!(handler.len(), 1);
+    assert_eq!(attribute(handler[0], "sku").as_deref(), Some("OTEL-DESK"));
+    assert_eq!(
+        attribute(handler[0], "order_id").as_deref(),
+        Some("otel-bus-order")
+    );
+    assert!(
+        spans
+            .iter()
+            .any(|span| span.name.starts_with("repository."))
+    );
+}
+
+#[tokio::test]
+async fn test_api_key_id_is_recorded_on_request_span() {
+    LazyLock::force(&RECORDER);
+    let app = TestApp::new().await;
+    let (status, body) = app
+        .post_json(
+            "/admin/api-keys",
+            json!({ "name": "otel-service", "scopes": ["inventory"] }),
+        )
+        .await;
+    assert_eq!(status, 201);
+    let id = body["id"].as_str().unwrap();
+
+    let request = Request::post("/add_batch")
+        .header("content-type", "application/json")
+        .header(
+            "authorization",
+            format!("ApiKey {}", body["key"].as_str().unwrap()),
+        )
+        .body(Body::from(
+            json!({ "reference": "otel-key-batch", "sku": "OTEL-KEY", "qty": 10 }).to_string(),
+        ))
+        .unwrap();
+    assert_eq!(app.send(request).await.status(), 201);
+
+    let spans = trace("sitemap", "api_key_id", id).await;
+    assert_eq!(named(&spans, "sitemap").len(), 1);
+}
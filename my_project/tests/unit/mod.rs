@@ -1 +1,4 @@
 pub mod test_batches;
+pub mod test_configures;
+pub mod test_rolling;
+pub mod test_strategies;
@@ -1,4 +1,4 @@
-use architecture::chapter1::{Batch, OrderLine, allocate};
+use architecture::chapter1::{Allocated, AllocationError, Batch, OrderLine, Product, allocate};
 use chrono::Utc;
 
 #[test]
@@ -156,6 +156,118 @@ fn test_raises_out_of_stock_exception_if_cannot_allocate() {
 
     assert_eq!(
         allocate(&line, vec![&mut batch]).unwrap_err(),
-        "Out of stock for sku SMALL-FORK".to_string()
+        AllocationError::OutOfStock("SMALL-FORK".to_string())
     );
 }
+
+#[test]
+fn test_partial_fill_splits_line_across_batches() {
+    let mut product = Product::new(
+        "SPLIT-LAMP",
+        vec![
+            Batch::new("in-stock", "SPLIT-LAMP", 60, None),
+            Batch::new("shipment", "SPLIT-LAMP", 60, Some(Utc::now())),
+        ],
+    );
+    let line = OrderLine {
+        order_id: "order1".to_string(),
+        sku: "SPLIT-LAMP".to_string(),
+        qty: 100,
+    };
+
+    assert!(product.allocate(&line).is_err());
+
+    let (parts, _) = product.allocate_parts(&line).unwrap().into_new().unwrap();
+    assert_eq!(
+        parts,
+        vec![("in-stock".to_string(), 60), ("shipment".to_string(), 40)]
+    );
+    assert_eq!(product.batches[0].available_quantity(), 0);
+    assert_eq!(product.batches[1].available_quantity(), 20);
+
+    // 重複分配同一訂單不會再次扣量
+    assert_eq!(
+        product.allocate_parts(&line).unwrap(),
+        Allocated::Existing(parts)
+    );
+    assert_eq!(product.batches[1].available_quantity(), 20);
+
+    // 數量不同的重試不會改變既有的分配
+    let changed = OrderLine { qty: 80, ..line };
+    assert_eq!(
+        product.allocate_parts(&changed).unwrap_err(),
+        AllocationError::AlreadyAllocated {
+            order_id: "order1".to_string(),
+            sku: "SPLIT-LAMP".to_string(),
+            qty: 100,
+        }
+    );
+    assert!(product.allocate(&changed).is_err());
+    assert_eq!(product.batches[1].available_quantity(), 20);
+}
+
+#[test]
+fn test_partial_fill_uses_single_batch_when_possible() {
+    let mut product = Product::new(
+        "SPLIT-DESK",
+        vec![
+            Batch::new("small", "SPLIT-DESK", 10, None),
+            Batch::new("large", "SPLIT-DESK", 100, None),
+        ],
+    );
+    let line = OrderLine {
+        order_id: "order1".to_string(),
+        sku: "SPLIT-DESK".to_string(),
+        qty: 50,
+    };
+
+    let (parts, _) = product.allocate_parts(&line).unwrap().into_new().unwrap();
+    assert_eq!(parts, vec![("large".to_string(), 50)]);
+}
+
+#[test]
+fn test_partial_fill_is_out_of_stock_when_total_is_insufficient() {
+    let mut product = Product::new(
+        "SPLIT-CHAIR",
+        vec![
+            Batch::new("b1", "SPLIT-CHAIR", 30, None),
+            Batch::new("b2", "SPLIT-CHAIR", 30, None),
+        ],
+    );
+    let line = OrderLine {
+        order_id: "order1".to_string(),
+        sku: "SPLIT-CHAIR".to_string(),
+        qty: 61,
+    };
+
+    assert_eq!(
+        product.allocate_parts(&line).unwrap_err(),
+        AllocationError::OutOfStock("SPLIT-CHAIR".to_string())
+    );
+    assert_eq!(product.batches[0].available_quantity(), 30);
+}
+
+#[test]
+fn test_deallocate_releases_every_part_of_a_split_line() {
+    let mut product = Product::new(
+        "SPLIT-SOFA",
+        vec![
+            Batch::new("b1", "SPLIT-SOFA", 60, None),
+            Batch::new("b2", "SPLIT-SOFA", 60, None),
+        ],
+    );
+    let line = OrderLine {
+        order_id: "order1".to_string(),
+        sku: "SPLIT-SOFA".to_string(),
+        qty: 100,
+    };
+    product.allocate_parts(&line).unwrap();
+
+    let released = product.deallocate("order1");
+    assert_eq!(
+        released,
+        vec![("b1".to_string(), 60), ("b2".to_string(), 40)]
+    );
+    assert!(product.batches.iter().all(|b| b.available_quantity() == 60));
+    assert!(product.allocations_for("order1").is_empty());
+}
@@ -0,0 +1,512 @@
+use architecture::chapter1::AllocationStrategyKind;
+use architecture::configures::{
+    AllocationConfig, AppConfig, ConfigSource, DatabaseConfig, JournalMode, LogFormat, LogRotation,
+    LoggerConfig, Profile, ReservationConfig, Role, RollPolicy, SecretConfig, ServerConfig,
+    Synchronous,
+};
+
+use crate::support::ENV;
+
+fn write_config(dir: &tempfile::TempDir, content: &str) -> std::path::PathBuf {
+    let path = dir.path().join("custom.toml");
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn test_builder_uses_defaults_for_missing_sections() {
+    let config = AppConfig::builder()
+        .server(ServerConfig {
+            port: Some(8080),
+            ..Default::default()
+        })
+        .allocation(AllocationConfig {
+            strategy: Some(AllocationStrategyKind::Fifo),
+            products: None,
+        })
+        .build();
+
+    assert_eq!(config.server.address(), "0.0.0.0:8080");
+    assert_eq!(config.server.app_env(), "development");
+    assert_eq!(
+        config.allocation.strategy_for("ANY"),
+        AllocationStrategyKind::Fifo
+    );
+    assert_eq!(
+        config.reservation.ttl(),
+        Some(chrono::Duration::seconds(300))
+    );
+    assert!(!config.arrival.reallocate());
+}
+
+#[test]
+fn test_load_from_reads_given_file() {
+    let _env = ENV.blocking_lock();
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_config(
+        &dir,
+        r#"
+        [server]
+        port = 9000
+
+        [database]
+        database = "mysql"
+
+        [reservation]
+        ttl_seconds = 60
+        "#,
+    );
+
+    let config = AppConfig::load_from(&path).unwrap();
+    assert_eq!(config.server.address(), "0.0.0.0:9000");
+    assert_eq!(
+        config.reservation.ttl(),
+        Some(chrono::Duration::seconds(60))
+    );
+
+    assert!(AppConfig::load_from(dir.path().join("missing.toml")).is_err());
+
+    let path = write_config(
+        &dir,
+        "[database]\ndatabase = \"mysql\"\n\n[reservation]\nttl_seconds = 9223372036854775807\n",
+    );
+    let err = AppConfig::load_from(&path).unwrap_err();
+    assert_eq!(err.issues()[0].key, "reservation.ttl_seconds");
+    // builder 不經過檢查，超出範圍時 ttl() 為 None 而不是 panic
+    let reservation = ReservationConfig {
+        ttl_seconds: Some(u64::MAX),
+        ..Default::default()
+    };
+    assert_eq!(reservation.ttl(), None);
+}
+
+#[test]
+fn test_load_from_reports_key_path_and_source() {
+    let _env = ENV.blocking_lock();
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_config(
+        &dir,
+        r#"
+        [server]
+        port = 0
+
+        [database]
+        pswd = "password"
+
+        [logger]
+        level = "verbose"
+        "#,
+    );
+
+    let err = AppConfig::load_from(&path).unwrap_err();
+    let issues: Vec<_> = err.issues().iter().map(|i| i.key.as_str()).collect();
+    assert_eq!(
+        issues,
+        vec![
+            "database.pswd",
+            "database.database",
+            "server.port",
+            "logger.level"
+        ]
+    );
+    assert!(matches!(&err.issues()[0].source, ConfigSource::File(f) if f.ends_with("custom.toml")));
+    assert!(err.to_string().contains("server.port"));
+    assert!(err.to_string().contains("invalid log level `verbose`"));
+}
+
+#[test]
+fn test_load_from_reports_env_var_source() {
+    let _env = ENV.blocking_lock();
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_config(&dir, "[database]\ndatabase = \"mysql\"\n");
+
+    unsafe { std::env::set_var("APP_SERVER_PORT", "70000") };
+    let result = AppConfig::load_from(&path);
+    unsafe { std::env::remove_var("APP_SERVER_PORT") };
+
+    let err = result.unwrap_err();
+    assert_eq!(err.issues().len(), 1);
+    assert_eq!(err.issues()[0].key, "server.port");
+    assert_eq!(
+        err.issues()[0].source,
+        ConfigSource::Env("APP_SERVER_PORT".to_string())
+    );
+}
+
+#[test]
+fn test_env_vars_set_multi_word_keys_and_ignore_unrelated_names() {
+    let _env = ENV.blocking_lock();
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_config(&dir, "[database]\ndatabase = \"mysql\"\n");
+
+    unsafe {
+        std::env::set_var("APP_DATABASE_MAX_CONNECTIONS", "3");
+        std::env::set_var("APP_SECRET_JWT_SECRET", "from-env");
+        std::env::set_var("APP_HOME", "/srv/app");
+    }
+    let result = AppConfig::load_from(&path);
+    unsafe {
+        std::env::remove_var("APP_DATABASE_MAX_CONNECTIONS");
+        std::env::remove_var("APP_SECRET_JWT_SECRET");
+        std::env::remove_var("APP_HOME");
+    }
+
+    let config = result.unwrap();
+    assert_eq!(config.database.max_connections, Some(3));
+    assert_eq!(config.secret.jwt_secret.as_deref(), Some("from-env"));
+}
+
+#[test]
+fn test_redacted_hides_secrets() {
+    let config = AppConfig::builder()
+        .database(DatabaseConfig {
+            password: Some("password".to_string()),
+            database: Some("mysql".to_string()),
+            ..Default::default()
+        })
+        .secret(SecretConfig {
+            jwt_secret: Some("jwt".to_string()),
+            refresh_secret: None,
+        })
+        .build();
+
+    let redacted = config.redacted();
+    assert_eq!(redacted["database"]["password"], "******");
+    assert_eq!(redacted["database"]["database"], "mysql");
+    assert_eq!(redacted["secret"]["jwt_secret"], "******");
+    assert!(redacted["secret"]["refresh_secret"].is_null());
+}
+
+#[test]
+fn test_profile_overlay_sits_between_file_and_env() {
+    let _env = ENV.blocking_lock();
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_config(
+        &dir,
+        r#"
+        [server]
+        env = "development"
+
+        [database]
+        database = "mysql"
+
+        [logger]
+        level = "debug"
+
+        [secret]
+        jwt_secret = "0123456789abcdef0123456789abcdef"
+        refresh_secret = "fedcba9876543210fedcba9876543210"
+        "#,
+    );
+    std::fs::write(
+        dir.path().join("custom.production.toml"),
+        "[logger]\nlevel = \"warn\"\n",
+    )
+    .unwrap();
+
+    let config = AppConfig::load_from(&path).unwrap();
+    assert_eq!(config.server.profile(), Profile::Development);
+    assert_eq!(config.logger.level.as_deref(), Some("debug"));
+    assert!(config.database.auto_migrate_for(config.server.profile()));
+
+    unsafe { std::env::set_var("APP_SERVER_ENV", "production") };
+    let production = AppConfig::load_from(&path);
+    unsafe { std::env::set_var("APP_LOGGER_LEVEL", "error") };
+    let overridden = AppConfig::load_from(&path);
+    unsafe {
+        std::env::remove_var("APP_SERVER_ENV");
+        std::env::remove_var("APP_LOGGER_LEVEL");
+    }
+
+    let config = production.unwrap();
+    let profile = config.server.profile();
+    assert_eq!(profile, Profile::Production);
+    assert_eq!(config.logger.level.as_deref(), Some("warn"));
+    assert_eq!(config.logger.format_for(profile), LogFormat::Compact);
+    assert!(!config.database.auto_migrate_for(profile));
+
+    assert_eq!(overridden.unwrap().logger.level.as_deref(), Some("error"));
+}
+
+#[test]
+fn test_production_requires_real_secrets() {
+    let _env = ENV.blocking_lock();
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_config(
+        &dir,
+        r#"
+        [server]
+        env = "production"
+
+        [database]
+        database = "mysql"
+
+        [secret]
+        jwt_secret = "your_jwt_secret_here"
+        "#,
+    );
+
+    let err = AppConfig::load_from(&path).unwrap_err();
+    let issues: Vec<_> = err
+        .issues()
+        .iter()
+        .map(|i| (i.key.as_str(), i.message.as_str()))
+        .collect();
+    assert_eq!(
+        issues,
+        vec![
+            (
+                "secret.jwt_secret",
+                "placeholder secret must be replaced in production"
+            ),
+            (
+                "secret.refresh_secret",
+                "required in production (set APP_SECRET_REFRESH_SECRET)"
+            ),
+        ]
+    );
+
+    unsafe {
+        std::env::set_var("APP_SECRET_JWT_SECRET", "too-short");
+        std::env::set_var("APP_SECRET_REFRESH_SECRET", "r".repeat(32));
+    }
+    let result = AppConfig::load_from(&path);
+    unsafe {
+        std::env::remove_var("APP_SECRET_JWT_SECRET");
+        std::env::remove_var("APP_SECRET_REFRESH_SECRET");
+    }
+
+    let err = result.unwrap_err();
+    assert_eq!(err.issues().len(), 1);
+    assert_eq!(
+        err.issues()[0].source,
+        ConfigSource::Env("APP_SECRET_JWT_SECRET".to_string())
+    );
+    assert_eq!(
+        err.issues()[0].message,
+        "must be at least 32 characters in production"
+    );
+}
+
+#[test]
+fn test_unknown_profile_is_rejected() {
+    let _env = ENV.blocking_lock();
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_config(
+        &dir,
+        "[server]\nenv = \"staging\"\n\n[database]\ndatabase = \"mysql\"\n",
+    );
+
+    let err = AppConfig::load_from(&path).unwrap_err();
+    assert_eq!(err.issues().len(), 1);
+    assert_eq!(err.issues()[0].key, "server.env");
+}
+
+#[test]
+fn test_database_tuning_is_read_from_file() {
+    let _env = ENV.blocking_lock();
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_config(
+        &dir,
+        r#"
+        [database]
+        database = "mysql"
+        max_connections = 4
+        idle_timeout_seconds = 0
+        busy_timeout_ms = 250
+        journal_mode = "delete"
+        synchronous = "full"
+
+        [database.pragmas]
+        cache_size = -20000
+        "#,
+    );
+
+    let config = AppConfig::load_from(&path).unwrap();
+    let database = &config.database;
+    assert_eq!(database.max_connections(), 4);
+    assert_eq!(database.idle_timeout(), None);
+    assert_eq!(database.journal_mode(), JournalMode::Delete);
+    assert_eq!(database.synchronous(), Synchronous::Full);
+    assert_eq!(
+        database.summary(),
+        "driver=sqlite max_connections=4 min_connections=0 acquire_timeout=30s idle_timeout=never \
+         busy_timeout=250ms journal_mode=delete synchronous=full foreign_keys=true cache_size=-20000"
+    );
+}
+
+#[test]
+fn test_invalid_database_tuning_is_rejected() {
+    let _env = ENV.blocking_lock();
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_config(
+        &dir,
+        r#"
+        [database]
+        database = "mysql"
+        max_connections = 2
+        min_connections = 5
+        journal_mode = "fast"
+        "#,
+    );
+
+    let err = AppConfig::load_from(&path).unwrap_err();
+    let issues: Vec<_> = err.issues().iter().map(|i| i.key.as_str()).collect();
+    assert_eq!(
+        issues,
+        vec!["database.journal_mode", "database.min_connections"]
+    );
+    assert!(err.to_string().contains("unknown value `fast`"));
+}
+
+#[test]
+fn test_logger_json_format_and_optional_split_files() {
+    let _env = ENV.blocking_lock();
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_config(
+        &dir,
+        "[database]\ndatabase = \"mysql\"\n\n[logger]\nformat = \"json\"\nsplit_by_level = false\n",
+    );
+
+    let config = AppConfig::load_from(&path).unwrap();
+    assert_eq!(
+        config.logger.format_for(Profile::Development),
+        LogFormat::Json
+    );
+    assert!(!config.logger.split_by_level());
+    assert!(AppConfig::default().logger.split_by_level());
+
+    let path = write_config(
+        &dir,
+        "[database]\ndatabase = \"mysql\"\n\n[logger]\nformat = \"xml\"\n",
+    );
+    let err = AppConfig::load_from(&path).unwrap_err();
+    assert_eq!(err.issues()[0].key, "logger.format");
+    assert!(
+        err.to_string()
+            .contains("expected one of pretty, compact, json")
+    );
+}
+
+#[test]
+fn test_logger_rotation_settings() {
+    let _env = ENV.blocking_lock();
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_config(
+        &dir,
+        "[database]\ndatabase = \"mysql\"\n\n[logger]\nrotation = \"size\"\nmax_file_size_mb = 5\nmax_files = 0\ncompress = true\n",
+    );
+
+    let config = AppConfig::load_from(&path).unwrap();
+    assert_eq!(
+        config.logger.roll_policy(),
+        RollPolicy::Size(5 * 1024 * 1024)
+    );
+    assert_eq!(config.logger.max_files(), None);
+    assert!(config.logger.compress());
+
+    let defaults = AppConfig::default().logger;
+    assert_eq!(defaults.roll_policy(), RollPolicy::Hourly);
+    assert_eq!(defaults.max_files(), Some(72));
+
+    let path = write_config(
+        &dir,
+        "[database]\ndatabase = \"mysql\"\n\n[logger]\nrotation = \"weekly\"\nmax_file_size_mb = 0\n",
+    );
+    let err = AppConfig::load_from(&path).unwrap_err();
+    let issues: Vec<_> = err.issues().iter().map(|i| i.key.as_str()).collect();
+    assert_eq!(issues, vec!["logger.rotation", "logger.max_file_size_mb"]);
+
+    let path = write_config(
+        &dir,
+        "[database]\ndatabase = \"mysql\"\n\n[logger]\nmax_file_size_mb = 17592186044416\n",
+    );
+    let err = AppConfig::load_from(&path).unwrap_err();
+    assert_eq!(err.issues()[0].key, "logger.max_file_size_mb");
+    assert!(err.to_string().contains("must be at most 10240"));
+
+    // builder 不經過檢查，換算成位元組時不會溢位
+    let logger = LoggerConfig {
+        rotation: Some(LogRotation::Size),
+        max_file_size_mb: Some(u64::MAX),
+        ..Default::default()
+    };
+    assert_eq!(logger.roll_policy(), RollPolicy::Size(u64::MAX));
+}
+
+#[test]
+fn test_invalid_otlp_endpoint_is_rejected() {
+    let _env = ENV.blocking_lock();
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_config(
+        &dir,
+        "[database]\ndatabase = \"mysql\"\n\n[logger.otlp]\nendpoint = \"localhost 4318\"\n",
+    );
+
+    let err = AppConfig::load_from(&path).unwrap_err();
+    assert_eq!(err.issues().len(), 1);
+    assert_eq!(err.issues()[0].key, "logger.otlp.endpoint");
+    assert_eq!(
+        err.issues()[0].message,
+        "invalid endpoint `localhost 4318`, expected an http(s) URL"
+    );
+
+    let path = write_config(
+        &dir,
+        "[database]\ndatabase = \"mysql\"\n\n[logger.otlp]\nendpoint = \"http://localhost:4318/v1/traces\"\n",
+    );
+    assert!(AppConfig::load_from(&path).is_ok());
+}
+
+#[test]
+fn test_auth_users_roles_are_validated_and_hashes_redacted() {
+    let _env = ENV.blocking_lock();
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_config(
+        &dir,
+        r#"
+        [database]
+        database = "mysql"
+
+        [auth]
+        access_ttl_seconds = 60
+
+        [[auth.users]]
+        username = "clerk"
+        password_hash = "$argon2id$v=19$m=19456,t=2,p=1$c2FsdA$aGFzaA"
+        roles = ["inventory", "sales"]
+        "#,
+    );
+
+    let config = AppConfig::load_from(&path).unwrap();
+    assert_eq!(config.auth.access_ttl().unwrap().num_seconds(), 60);
+    assert_eq!(config.auth.refresh_ttl().unwrap().num_days(), 7);
+    let clerk = config.auth.user("clerk").unwrap();
+    assert_eq!(clerk.roles, vec![Role::Inventory, Role::Sales]);
+    assert!(config.auth.user("nobody").is_none());
+
+    let redacted = config.redacted();
+    assert_eq!(redacted["auth"]["users"][0]["password_hash"], "******");
+    assert_eq!(redacted["auth"]["users"][0]["username"], "clerk");
+
+    let path = write_config(
+        &dir,
+        "[database]\ndatabase = \"mysql\"\n\n[[auth.users]]\nusername = \"x\"\npassword_hash = \"\"\nroles = [\"sales\", \"root\"]\n",
+    );
+    let err = AppConfig::load_from(&path).unwrap_err();
+    let issues: Vec<_> = err.issues().iter().map(|i| i.key.as_str()).collect();
+    assert_eq!(issues, vec!["auth.users[0].roles"]);
+    assert!(err.to_string().contains("unknown role `root`"));
+
+    let path = write_config(
+        &dir,
+        "[database]\ndatabase = \"mysql\"\n\n[auth]\naccess_ttl_seconds = 86401\nrefresh_ttl_seconds = 9223372036854775807\n",
+    );
+    let err = AppConfig::load_from(&path).unwrap_err();
+    let issues: Vec<_> = err.issues().iter().map(|i| i.key.as_str()).collect();
+    assert_eq!(
+        issues,
+        vec!["auth.access_ttl_seconds", "auth.refresh_ttl_seconds"]
+    );
+    assert!(err.to_string().contains("must be at most 86400"));
+}
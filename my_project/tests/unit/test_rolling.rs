@@ -0,0 +1,88 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::time::{Duration, SystemTime};
+
+use architecture::configures::{RollPolicy, RollingFile};
+use chrono::{DateTime, Local};
+use flate2::read::GzDecoder;
+
+fn rotated(dir: &tempfile::TempDir, name: &str) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .filter(|file| file.starts_with(&format!("{}.", name)))
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_size_rotation_compresses_and_keeps_max_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut file = RollingFile::new(
+        dir.path(),
+        "app_all.log",
+        RollPolicy::Size(10),
+        Some(2),
+        true,
+    )
+    .unwrap();
+
+    for line in [
+        "first-line\n",
+        "second-line\n",
+        "third-line\n",
+        "fourth-line\n",
+    ] {
+        file.write_all(line.as_bytes()).unwrap();
+        // 讓修改時間可區分先後
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    file.flush().unwrap();
+
+    assert_eq!(
+        fs::read_to_string(dir.path().join("app_all.log")).unwrap(),
+        "fourth-line\n"
+    );
+
+    // 壓縮在背景執行緒，drop 時等待完成
+    drop(file);
+    let rotated = rotated(&dir, "app_all.log");
+    assert_eq!(rotated.len(), 2);
+    assert!(rotated.iter().all(|name| name.ends_with(".gz")));
+
+    let mut contents = Vec::new();
+    for name in &rotated {
+        let mut text = String::new();
+        GzDecoder::new(fs::File::open(dir.path().join(name)).unwrap())
+            .read_to_string(&mut text)
+            .unwrap();
+        contents.push(text);
+    }
+    contents.sort();
+    assert_eq!(contents, vec!["second-line\n", "third-line\n"]);
+}
+
+#[test]
+fn test_daily_rotation_rolls_file_left_from_previous_day() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app_info.log");
+    fs::write(&path, "yesterday\n").unwrap();
+    let yesterday = SystemTime::now() - Duration::from_secs(24 * 3600);
+    fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(yesterday)
+        .unwrap();
+
+    let mut file =
+        RollingFile::new(dir.path(), "app_info.log", RollPolicy::Daily, None, false).unwrap();
+    file.write_all(b"today\n").unwrap();
+    file.flush().unwrap();
+
+    let stamp = DateTime::<Local>::from(yesterday).format("%Y-%m-%d");
+    let rolled = dir.path().join(format!("app_info.log.{}", stamp));
+    assert_eq!(fs::read_to_string(rolled).unwrap(), "yesterday\n");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "today\n");
+}
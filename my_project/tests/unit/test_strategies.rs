@@ -0,0 +1,248 @@
+use architecture::chapter1::{
+    AllocationStrategyKind, Batch, FifoByCreation, NearestWarehouse, OrderLine, Product,
+    SmallestSufficientBatch, StockFirstEarliestEta, allocate_with,
+};
+use architecture::configures;
+use chrono::{Duration, Utc};
+
+fn line(sku: &str, qty: u32) -> OrderLine {
+    OrderLine {
+        order_id: "order-001".to_string(),
+        sku: sku.to_string(),
+        qty,
+    }
+}
+
+#[test]
+fn test_stock_first_prefers_warehouse_stock_then_earliest_eta() {
+    let now = Utc::now();
+    let mut later = Batch::new("later", "RETRO-CLOCK", 100, Some(now + Duration::days(2)));
+    let mut earlier = Batch::new("earlier", "RETRO-CLOCK", 100, Some(now + Duration::days(1)));
+    let mut in_stock = Batch::new("in-stock", "RETRO-CLOCK", 100, None);
+
+    let allocated = allocate_with(
+        &line("RETRO-CLOCK", 10),
+        vec![&mut later, &mut earlier, &mut in_stock],
+        &StockFirstEarliestEta,
+    )
+    .unwrap();
+    assert_eq!(allocated, Some("in-stock".to_string()));
+
+    let mut product = Product::new(
+        "RETRO-CLOCK",
+        vec![
+            Batch::new("later", "RETRO-CLOCK", 100, Some(now + Duration::days(2))),
+            Batch::new("earlier", "RETRO-CLOCK", 100, Some(now + Duration::days(1))),
+        ],
+    )
+    .with_strategy(Box::new(StockFirstEarliestEta));
+
+    let (batch_ref, _) = product
+        .allocate(&line("RETRO-CLOCK", 10))
+        .unwrap()
+        .into_new()
+        .unwrap();
+    assert_eq!(batch_ref, "earlier");
+}
+
+#[test]
+fn test_smallest_sufficient_batch_keeps_large_batches_free() {
+    let mut product = Product::new(
+        "MINIMALIST-SPOON",
+        vec![
+            Batch::new("large", "MINIMALIST-SPOON", 100, None),
+            Batch::new("too-small", "MINIMALIST-SPOON", 5, None),
+            Batch::new("just-enough", "MINIMALIST-SPOON", 20, None),
+        ],
+    )
+    .with_strategy(Box::new(SmallestSufficientBatch));
+
+    let (batch_ref, _) = product
+        .allocate(&line("MINIMALIST-SPOON", 10))
+        .unwrap()
+        .into_new()
+        .unwrap();
+    assert_eq!(batch_ref, "just-enough");
+}
+
+#[test]
+fn test_fifo_allocates_to_oldest_batch_first() {
+    let now = Utc::now();
+    let mut product = Product::new(
+        "HIGHBROW-POSTER",
+        vec![
+            Batch::new("no-date", "HIGHBROW-POSTER", 100, None),
+            Batch::new("newest", "HIGHBROW-POSTER", 100, None).with_created_at(now),
+            Batch::new(
+                "oldest",
+                "HIGHBROW-POSTER",
+                100,
+                Some(now + Duration::days(7)),
+            )
+            .with_created_at(now - Duration::days(3)),
+        ],
+    )
+    .with_strategy(Box::new(FifoByCreation));
+
+    let (batch_ref, _) = product
+        .allocate(&line("HIGHBROW-POSTER", 10))
+        .unwrap()
+        .into_new()
+        .unwrap();
+    assert_eq!(batch_ref, "oldest");
+}
+
+#[test]
+fn test_strategy_kind_builds_matching_strategy() {
+    let now = Utc::now();
+    let batches = || {
+        vec![
+            Batch::new("shipment-small", "SMALL-FORK", 15, Some(now))
+                .with_created_at(now - Duration::days(1)),
+            Batch::new("stock-large", "SMALL-FORK", 50, None)
+                .with_created_at(now - Duration::days(5)),
+            Batch::new("stock-small", "SMALL-FORK", 12, None).with_created_at(now),
+        ]
+    };
+
+    let expected = [
+        (AllocationStrategyKind::StockFirst, "stock-large"),
+        (AllocationStrategyKind::SmallestSufficient, "stock-small"),
+        (AllocationStrategyKind::Fifo, "stock-large"),
+    ];
+
+    for (kind, expected_ref) in expected {
+        let mut product = Product::new("SMALL-FORK", batches()).with_strategy(kind.strategy());
+        let (batch_ref, _) = product
+            .allocate(&line("SMALL-FORK", 10))
+            .unwrap()
+            .into_new()
+            .unwrap();
+        assert_eq!(batch_ref, expected_ref, "strategy {:?}", kind);
+    }
+}
+
+#[test]
+fn test_allocation_config_selects_strategy_per_product() {
+    let config: configures::AppConfig = toml_config(
+        r#"
+        [allocation]
+        strategy = "fifo"
+
+        [allocation.products]
+        "SMALL-TABLE" = "smallest_sufficient"
+        "#,
+    );
+
+    assert_eq!(
+        config.allocation.strategy_for("SMALL-TABLE"),
+        AllocationStrategyKind::SmallestSufficient
+    );
+    assert_eq!(
+        config.allocation.strategy_for("OTHER"),
+        AllocationStrategyKind::Fifo
+    );
+
+    let config: configures::AppConfig = toml_config("");
+    assert_eq!(
+        config.allocation.strategy_for("OTHER"),
+        AllocationStrategyKind::StockFirst
+    );
+}
+
+#[test]
+fn test_nearest_warehouse_prefers_region_order_then_falls_back() {
+    let now = Utc::now();
+    let warehouse = |w: &str| Some(w.to_string());
+    let batches = || {
+        vec![
+            Batch::new("tpe", "TALL-LAMP", 100, None).with_warehouse(warehouse("TPE")),
+            Batch::new("khh-later", "TALL-LAMP", 100, Some(now + Duration::days(2)))
+                .with_warehouse(warehouse("KHH")),
+            Batch::new(
+                "khh-earlier",
+                "TALL-LAMP",
+                100,
+                Some(now + Duration::days(1)),
+            )
+            .with_warehouse(warehouse("KHH")),
+            Batch::new("unknown", "TALL-LAMP", 100, None),
+        ]
+    };
+    let nearest = |preference: &[&str]| {
+        Box::new(NearestWarehouse::new(
+            preference.iter().map(|w| w.to_string()).collect(),
+            Box::new(StockFirstEarliestEta),
+        ))
+    };
+
+    // 同一倉庫內依原策略排序
+    let mut product = Product::new("TALL-LAMP", batches()).with_strategy(nearest(&["KHH", "TPE"]));
+    let (batch_ref, _) = product
+        .allocate(&line("TALL-LAMP", 10))
+        .unwrap()
+        .into_new()
+        .unwrap();
+    assert_eq!(batch_ref, "khh-earlier");
+
+    // 最近倉庫庫存不足時改用下一個倉庫
+    let mut product = Product::new("TALL-LAMP", batches()).with_strategy(nearest(&["KHH", "TPE"]));
+    assert!(product.allocate(&line("TALL-LAMP", 150)).is_err());
+    let (batch_ref, _) = product
+        .allocate(&line("TALL-LAMP", 100))
+        .unwrap()
+        .into_new()
+        .unwrap();
+    assert_eq!(batch_ref, "khh-earlier");
+    let mut second = line("TALL-LAMP", 100);
+    second.order_id = "order-002".to_string();
+    let (batch_ref, _) = product.allocate(&second).unwrap().into_new().unwrap();
+    assert_eq!(batch_ref, "khh-later");
+    let mut third = line("TALL-LAMP", 100);
+    third.order_id = "order-003".to_string();
+    let (batch_ref, _) = product.allocate(&third).unwrap().into_new().unwrap();
+    assert_eq!(batch_ref, "tpe");
+
+    // 不在偏好清單中的倉庫排在後面，沒有倉庫的批次最後
+    let mut product = Product::new("TALL-LAMP", batches()).with_strategy(nearest(&["TXG"]));
+    let (batch_ref, _) = product
+        .allocate(&line("TALL-LAMP", 10))
+        .unwrap()
+        .into_new()
+        .unwrap();
+    assert_eq!(batch_ref, "tpe");
+}
+
+#[test]
+fn test_warehouse_config_maps_region_to_preference() {
+    let config: configures::AppConfig = toml_config(
+        r#"
+        [warehouse.regions]
+        south = ["KHH", "TPE"]
+        "#,
+    );
+
+    assert_eq!(
+        config.warehouse.preference_for("south"),
+        Some(vec!["KHH".to_string(), "TPE".to_string()])
+    );
+    assert_eq!(config.warehouse.preference_for("north"), None);
+}
+
+fn toml_config(extra: &str) -> configures::AppConfig {
+    let base = r#"
+        [server]
+        [database]
+        [logger]
+    "#;
+
+    config::Config::builder()
+        .add_source(config::File::from_str(
+            &format!("{}\n{}", base, extra),
+            config::FileFormat::Toml,
+        ))
+        .build()
+        .unwrap()
+        .try_deserialize()
+        .unwrap()
+}
@@ -27,25 +27,25 @@ fn to_snake_case(name: &str) -> String {
 ///   String -> ("String", false)
 ///   Option<i32> -> ("i32", true)
 fn get_type_info(ty: &Type) -> (String, bool) {
-    if let Type::Path(type_path) = ty {
-        if let Some(segment) = type_path.path.segments.last() {
-            if segment.ident == "Option" {
-                // 如果是 Option，解析角括號內的型別 <T>
-                if let PathArguments::AngleBracketed(args) = &segment.arguments {
-                    if let Some(GenericArgument::Type(inner_ty)) = args.args.first() {
-                        // 遞迴呼叫或是直接取 inner type 的名稱
-                        // 這裡簡化處理，直接取 inner type 的最後一個 segment
-                        if let Type::Path(inner_path) = inner_ty {
-                            if let Some(inner_seg) = inner_path.path.segments.last() {
-                                return (inner_seg.ident.to_string(), true);
-                            }
-                        }
-                    }
+    if let Type::Path(type_path) = ty
+        && let Some(segment) = type_path.path.segments.last()
+    {
+        if segment.ident == "Option" {
+            // 如果是 Option，解析角括號內的型別 <T>
+            if let PathArguments::AngleBracketed(args) = &segment.arguments
+                && let Some(GenericArgument::Type(inner_ty)) = args.args.first()
+            {
+                // 遞迴呼叫或是直接取 inner type 的名稱
+                // 這裡簡化處理，直接取 inner type 的最後一個 segment
+                if let Type::Path(inner_path) = inner_ty
+                    && let Some(inner_seg) = inner_path.path.segments.last()
+                {
+                    return (inner_seg.ident.to_string(), true);
                 }
-            } else {
-                // 不是 Option
-                return (segment.ident.to_string(), false);
             }
+        } else {
+            // 不是 Option
+            return (segment.ident.to_string(), false);
         }
     }
     panic!("Unsupported field type parsing");
@@ -120,7 +120,21 @@ pub fn sql_table(input: TokenStream) -> TokenStream {
         .map(|((ty, is_opt), field)| {
             let needs_quotes = matches!(
                 ty.as_str(),
-                "String" | "&str" | "NaiveDateTime" | "NaiveDate"
+                "String" | "&str" | "DateTime" | "NaiveDateTime" | "NaiveDate"
+            );
+            // DateTime<Tz> 以 RFC 3339 輸出，sqlx 才能再解碼回來
+            let (opt_val, val) = if ty == "DateTime" {
+                (
+                    quote! { v.to_rfc3339() },
+                    quote! { self.#field.to_rfc3339() },
+                )
+            } else {
+                (quote! { v }, quote! { self.#field })
+            };
+            // 字串常值中的單引號跳脫成兩個，與 repositories::quote 相同
+            let (opt_val, val) = (
+                quote! { #opt_val.to_string().replace('\'', "''") },
+                quote! { #val.to_string().replace('\'', "''") },
             );
 
             if *is_opt {
@@ -128,7 +142,7 @@ pub fn sql_table(input: TokenStream) -> TokenStream {
                 if needs_quotes {
                     quote! {
                         match &self.#field {
-                            Some(v) => format!("'{}'", v),
+                            Some(v) => format!("'{}'", #opt_val),
                             None => "NULL".to_string()
                         }
                     }
@@ -143,7 +157,7 @@ pub fn sql_table(input: TokenStream) -> TokenStream {
             } else {
                 // 如果不是 Option (原本的邏輯)
                 if needs_quotes {
-                    quote! { format!("'{}'", self.#field) }
+                    quote! { format!("'{}'", #val) }
                 } else {
                     quote! { format!("{}", self.#field) }
                 }
@@ -157,7 +171,21 @@ pub fn sql_table(input: TokenStream) -> TokenStream {
         .map(|((col, (ty, is_opt)), field)| {
             let needs_quotes = matches!(
                 ty.as_str(),
-                "String" | "&str" | "NaiveDateTime" | "NaiveDate"
+                "String" | "&str" | "DateTime" | "NaiveDateTime" | "NaiveDate"
+            );
+            // DateTime<Tz> 以 RFC 3339 輸出，sqlx 才能再解碼回來
+            let (opt_val, val) = if ty == "DateTime" {
+                (
+                    quote! { v.to_rfc3339() },
+                    quote! { self.#field.to_rfc3339() },
+                )
+            } else {
+                (quote! { v }, quote! { self.#field })
+            };
+            // 字串常值中的單引號跳脫成兩個，與 repositories::quote 相同
+            let (opt_val, val) = (
+                quote! { #opt_val.to_string().replace('\'', "''") },
+                quote! { #val.to_string().replace('\'', "''") },
             );
 
             if *is_opt {
@@ -165,7 +193,7 @@ pub fn sql_table(input: TokenStream) -> TokenStream {
                 if needs_quotes {
                     quote! {
                         match &self.#field {
-                            Some(v) => Some(format!("{}='{}'", #col, v)),
+                            Some(v) => Some(format!("{}='{}'", #col, #opt_val)),
                             None => None
                         }
                     }
@@ -180,7 +208,7 @@ pub fn sql_table(input: TokenStream) -> TokenStream {
             } else {
                 // 如果不是 Option (原本的邏輯)
                 if needs_quotes {
-                    quote! { Some(format!("{}='{}'", #col, self.#field)) }
+                    quote! { Some(format!("{}='{}'", #col, #val)) }
                 } else {
                     quote! { Some(format!("{}={}", #col, self.#field)) }
                 }